@@ -1,84 +1,795 @@
-use anyhow::Result;
-use clap::Parser;
-use iro::base24::{generate_palette, Base24Style, PaletteSettings, PaletteStyle};
-use iro::{parse_colors, ParseColorsSettings};
+mod preview;
+mod terminal;
 
-use std::path::PathBuf;
+use crate::preview::Preview;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use image::RgbImage;
+use iro::audit::{audit, AuditMetric};
+use iro::base24::{
+    generate_pair, neutral_palette, parse_slot, BackgroundTint, Base24Style, PaletteSettings,
+    PaletteStyle, ANSI_SLOTS,
+};
+use iro::contrast::ContrastMetric;
+use iro::cvd::{highlight_distances, simulate_palette, Deficiency};
+use iro::export::SchemeExport;
+use iro::extractor::ExtractionMethod;
+use iro::gamut::{gamut_report, Gamut};
+use iro::generator::{Ansi16Generator, Base24Generator, PaletteGenerator};
+use iro::highlights::{BrightDerivation, HighlightLightness};
+use iro::hues::{HueAssignment, HueFill};
+use iro::provenance::Provenance;
+use iro::ramp::Easing;
+use iro::settings::Settings;
+use iro::{
+    check_range, decode_image, hex_to_lch, mean_lightness, IroError, Oklch, ParseColorsSettings,
+};
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::iter;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Base24 YAML with hex colors
+    Yaml,
+    /// JSON with Oklch metadata and generation settings
+    Json,
+    /// TOML with Oklch metadata and generation settings
+    Toml,
+    /// CSS custom properties
+    Css,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// All 24 Base24 slots (base00 - base17)
+    Base24,
+    /// The 16 ANSI terminal colors (color0 - color15)
+    Ansi16,
+}
 
 /// Generate color schemes from images
 #[derive(Parser, Debug, Clone)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Path to the image
-    pub path: PathBuf,
+    #[arg(required = true)]
+    pub path: Option<PathBuf>,
 
-    /// Generates light color schemes when true
-    #[arg(short, long, default_value_t = false)]
+    /// Generates light color schemes when true (shorthand for --style light)
+    #[arg(short, long, default_value_t = false, conflicts_with = "style")]
     pub light: bool,
 
-    /// The number of colors to keep from the image
-    #[arg(short, long, default_value_t = 8)]
+    /// The style of color scheme to generate (auto picks dark or light from the image)
+    #[arg(long, value_enum, default_value_t = PaletteStyle::Dark)]
+    pub style: PaletteStyle,
+
+    /// The number of colors to keep from the image (1-8)
+    #[arg(short, long, default_value_t = 8, value_parser = in_range(PaletteSettings::KEEP))]
     pub keep: usize,
 
     /// How many positions to rotate the highlight colors
     #[arg(short, long, default_value_t = 0)]
     pub rotation: usize,
 
+    /// How extracted hues are assigned to highlight slots (semantic ignores rotation)
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = HueAssignment::Rotation)]
+    pub hue_assignment: HueAssignment,
+
+    /// How missing highlight hues are filled in when fewer than 8 colors are kept
+    #[arg(long, value_enum, value_name = "FILL", default_value_t = HueFill::Repeat)]
+    pub hue_fill: HueFill,
+
+    /// How colors are extracted from the image
+    #[arg(long, value_enum, value_name = "METHOD", default_value_t = ExtractionMethod::HueSegments)]
+    pub extractor: ExtractionMethod,
+
     /// The size (in degrees) of a color wheel segment that should be treated as a single hue
-    #[arg(short, long, default_value_t = 15.0)]
+    /// (1-360)
+    #[arg(
+        short,
+        long,
+        default_value_t = 15.0,
+        value_parser = in_range(ParseColorsSettings::SEGMENT_SIZE)
+    )]
     pub segment_size: f64,
 
-    /// The chroma to use for base colors
-    #[arg(short, long)]
+    /// The number of colors k-means extraction clusters the image into (1-16)
+    #[arg(
+        long,
+        default_value_t = 8,
+        value_parser = in_range(ParseColorsSettings::CLUSTERS)
+    )]
+    pub clusters: usize,
+
+    /// The chroma to use for base colors (0-0.4)
+    #[arg(short, long, value_parser = in_range(PaletteSettings::CHROMA))]
     pub base_chroma: Option<f64>,
 
-    /// The lightness to use for highlight colors
-    #[arg(short, long)]
+    /// The hue of the base colors: accent, image (the image's background tone), neutral or
+    /// a hue in degrees
+    #[arg(long, value_name = "TINT", default_value = "accent")]
+    pub background: BackgroundTint,
+
+    /// The lightness of base00 (0-1, defaults depend on --light)
+    #[arg(long, value_name = "L", value_parser = in_range(PaletteSettings::LIGHTNESS))]
+    pub ramp_start: Option<f64>,
+
+    /// The lightness of base07 (0-1, defaults depend on --light)
+    #[arg(long, value_name = "L", value_parser = in_range(PaletteSettings::LIGHTNESS))]
+    pub ramp_end: Option<f64>,
+
+    /// How base colors are spaced: linear, ease-in, ease-out or positions like 0,0.1,0.3,1
+    #[arg(long, value_name = "EASING", default_value_t = Easing::Linear)]
+    pub easing: Easing,
+
+    /// How much of the base chroma fades out towards base07 (0-1)
+    #[arg(
+        long,
+        value_name = "TAPER",
+        default_value_t = 0.0,
+        value_parser = in_range(PaletteSettings::FRACTION)
+    )]
+    pub chroma_taper: f64,
+
+    /// The lightness to use for highlight colors (0-1)
+    #[arg(long, value_parser = in_range(PaletteSettings::LIGHTNESS))]
     pub hl_lightness: Option<f64>,
 
-    /// The chroma to use for highlight colors
-    #[arg(short, long)]
+    /// How each highlight's lightness is derived from --hl-lightness
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = HighlightLightness::Fixed)]
+    pub hl_lightness_mode: HighlightLightness,
+
+    /// How far source lightness may stray from --hl-lightness in source mode (0-1)
+    #[arg(
+        long,
+        value_name = "SPREAD",
+        default_value_t = 0.1,
+        value_parser = in_range(PaletteSettings::LIGHTNESS)
+    )]
+    pub hl_lightness_spread: f64,
+
+    /// Spread out highlights until their hues are at least this many degrees apart (0-180)
+    #[arg(
+        long,
+        value_name = "DEGREES",
+        value_parser = in_range(PaletteSettings::HUE_SEPARATION)
+    )]
+    pub min_hue_separation: Option<f64>,
+
+    /// Spread out highlights until they are at least this far apart (Oklab ΔE, 0-1)
+    #[arg(long, value_name = "DELTA_E", value_parser = in_range(PaletteSettings::DELTA_E))]
+    pub min_delta_e: Option<f64>,
+
+    /// How much highlights keep the lightness and chroma of the image colors (0-1)
+    #[arg(
+        long,
+        value_name = "FIDELITY",
+        default_value_t = 0.0,
+        value_parser = in_range(PaletteSettings::FRACTION)
+    )]
+    pub fidelity: f64,
+
+    /// How brights are derived: scale:<FACTOR>[,<CHROMA FACTOR>], offset:<L> or apca:<LC>
+    /// (defaults depend on --light)
+    #[arg(long, value_name = "MODE:AMOUNT")]
+    pub brights: Option<BrightDerivation>,
+
+    /// The chroma to use for bright highlight colors (0-0.4, derived by default)
+    #[arg(long, value_name = "CHROMA", value_parser = in_range(PaletteSettings::CHROMA))]
+    pub bright_chroma: Option<f64>,
+
+    /// The chroma to use for highlight colors (0-0.4)
+    #[arg(long, value_parser = in_range(PaletteSettings::CHROMA))]
     pub hl_chroma: Option<f64>,
+
+    /// The minimum contrast of foreground and highlight colors against the background
+    /// (1-21 for wcag, 0-108 for apca; defaults depend on --style)
+    #[arg(short, long)]
+    pub min_contrast: Option<f64>,
+
+    /// How contrast is measured for --min-contrast
+    #[arg(long, value_enum, value_name = "METRIC", default_value_t = ContrastMetric::Wcag)]
+    pub contrast_metric: ContrastMetric,
+
+    /// Nudge highlight hues until they are at least this far apart (Oklab ΔE) under simulated
+    /// color-vision deficiencies (0-1)
+    #[arg(long, value_name = "DELTA_E", value_parser = in_range(PaletteSettings::DELTA_E))]
+    pub cvd_min_delta_e: Option<f64>,
+
+    /// Lock a slot to a specific color, e.g. --set base08=#ff5555 (can be repeated)
+    #[arg(long = "set", value_name = "SLOT=HEX", value_parser = parse_override)]
+    pub overrides: Vec<(usize, Oklch<f64>)>,
+
+    /// Load generation settings from a JSON, TOML or YAML file instead of the options above
+    /// (missing settings are taken from the file's style preset)
+    #[arg(long, value_name = "FILE")]
+    pub settings: Option<PathBuf>,
+
+    /// Save the generation settings to a JSON or TOML file
+    #[arg(long, value_name = "FILE")]
+    pub save_settings: Option<PathBuf>,
+
+    /// Write matching dark and light schemes to <PREFIX>-dark and <PREFIX>-light files
+    #[arg(long, value_name = "PREFIX")]
+    pub pair: Option<String>,
+
+    /// Embed the source image hash and generation settings in the scheme
+    #[arg(long, default_value_t = false)]
+    pub provenance: bool,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Regenerate a scheme from the provenance embedded in it
+    Regenerate {
+        /// Path to a YAML scheme generated with --provenance
+        scheme: PathBuf,
+
+        /// Path to the source image (defaults to the path recorded in the scheme)
+        #[arg(short, long)]
+        image: Option<PathBuf>,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Check the contrast of every foreground and background pair in a scheme
+    Audit {
+        /// Path to a YAML scheme
+        scheme: PathBuf,
+
+        /// The contrast measurement that decides whether a check passes
+        #[arg(long, value_enum, default_value_t = AuditMetric::Wcag)]
+        metric: AuditMetric,
+
+        /// Print the report as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Check how distinguishable the highlights of a scheme are under color-vision deficiencies
+    Cvd {
+        /// Path to a YAML scheme
+        scheme: PathBuf,
+
+        /// Print the report as JSON
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
 }
 
-impl From<Args> for PaletteSettings {
-    fn from(args: Args) -> Self {
+#[derive(clap::Args, Debug, Clone)]
+pub struct OutputArgs {
+    /// The format of the generated scheme
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Yaml)]
+    pub format: OutputFormat,
+
+    /// The slots to emit in JSON, TOML and CSS output
+    #[arg(long, value_enum, default_value_t = Layout::Base24)]
+    pub layout: Layout,
+
+    /// Print a truecolor preview of the scheme instead of YAML
+    #[arg(long, default_value_t = false)]
+    pub show: bool,
+
+    /// Also emit colors for a wide gamut color space in JSON, TOML and CSS output
+    #[arg(short, long, value_enum, default_value_t = Gamut::Srgb)]
+    pub gamut: Gamut,
+
+    /// Simulate how the scheme appears with a color-vision deficiency
+    #[arg(long, value_enum)]
+    pub simulate: Option<Deficiency>,
+
+    /// Report which colors were gamut mapped into the target gamut, and by how much
+    #[arg(long, default_value_t = false)]
+    pub gamut_report: bool,
+
+    /// Recolor the current terminal with the scheme using OSC escape sequences
+    #[arg(long, default_value_t = false, conflicts_with = "show")]
+    pub apply: bool,
+
+    /// Print a shell script that recolors the terminal it is run in
+    #[arg(long, default_value_t = false, conflicts_with_all = ["show", "apply"])]
+    pub apply_script: bool,
+}
+
+/// A value parser that only accepts values within `range`
+fn in_range<T>(range: RangeInclusive<T>) -> impl Fn(&str) -> Result<T> + Clone + Send + Sync
+where
+    T: FromStr + PartialOrd + Display + Clone + Send + Sync,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    move |value| Ok(check_range("value", value.parse()?, &range)?)
+}
+
+fn parse_override(value: &str) -> Result<(usize, Oklch<f64>)> {
+    let (slot, hex) = value
+        .split_once('=')
+        .with_context(|| format!("expected SLOT=HEX, got {value:?}"))?;
+    Ok((parse_slot(slot.trim())?, hex_to_lch(hex.trim())?))
+}
+
+impl TryFrom<Args> for PaletteSettings {
+    type Error = IroError;
+
+    fn try_from(args: Args) -> iro::Result<Self> {
         let style = if args.light {
             PaletteStyle::Light
         } else {
-            PaletteStyle::Dark
+            args.style
         };
         let defaults = PaletteSettings::default_for(style);
-        Self {
-            style,
-            keep: args.keep,
-            rotation: args.rotation,
-            base_chroma: args.base_chroma.unwrap_or(defaults.base_chroma),
-            hl_chroma: args.hl_chroma.unwrap_or(defaults.hl_chroma),
-            hl_lightness: args.hl_lightness.unwrap_or(defaults.hl_lightness),
+        let builder = PaletteSettings::builder(style)
+            .keep(args.keep)
+            .rotation(args.rotation)
+            .hue_assignment(args.hue_assignment)
+            .hue_fill(args.hue_fill)
+            .base_chroma(args.base_chroma.unwrap_or(defaults.base_chroma))
+            .background_tint(args.background)
+            .ramp_start(args.ramp_start.unwrap_or(defaults.ramp.start))
+            .ramp_end(args.ramp_end.unwrap_or(defaults.ramp.end))
+            .easing(args.easing)
+            .chroma_taper(args.chroma_taper)
+            .hl_chroma(args.hl_chroma.unwrap_or(defaults.hl_chroma))
+            .hl_lightness(args.hl_lightness.unwrap_or(defaults.hl_lightness))
+            .hl_lightness_mode(args.hl_lightness_mode)
+            .hl_lightness_spread(args.hl_lightness_spread)
+            .min_hue_separation(args.min_hue_separation)
+            .min_delta_e(args.min_delta_e)
+            .fidelity(args.fidelity)
+            .brights(args.brights.unwrap_or(defaults.brights))
+            .bright_chroma(args.bright_chroma)
+            // Preset contrasts are measured with the preset's metric
+            .min_contrast(
+                args.min_contrast.or(defaults
+                    .min_contrast
+                    .filter(|_| defaults.contrast_metric == args.contrast_metric)),
+            )
+            .contrast_metric(args.contrast_metric)
+            .cvd_min_delta_e(args.cvd_min_delta_e);
+        args.overrides
+            .into_iter()
+            .fold(builder, |builder, (slot, color)| {
+                builder.override_slot(slot, color)
+            })
+            .build()
+    }
+}
+
+impl TryFrom<Args> for ParseColorsSettings {
+    type Error = IroError;
+
+    fn try_from(args: Args) -> iro::Result<Self> {
+        Self::new(args.extractor, args.segment_size, args.clusters)
+    }
+}
+
+/// Settings from the command line. An auto style takes the preset it resolves to for the
+/// image but stays auto (like image background tints), so saved settings can be reused
+/// with other images.
+fn settings_from_args(
+    mut args: Args,
+    image_lightness: f64,
+) -> Result<(PaletteSettings, ParseColorsSettings)> {
+    let parse_colors_settings: ParseColorsSettings = args.clone().try_into()?;
+    let auto = args.style == PaletteStyle::Auto && !args.light;
+    args.style = args.style.resolve(image_lightness);
+    let mut palette_settings: PaletteSettings = args.try_into()?;
+    if auto {
+        palette_settings.style = PaletteStyle::Auto;
+    }
+    Ok((palette_settings, parse_colors_settings))
+}
+
+/// Loads a JSON, TOML or YAML settings file, picking the preset for missing settings of an
+/// auto style from the image
+fn settings_from_file(path: &Path, image_lightness: f64) -> Result<Settings> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let image_lightness = Some(image_lightness);
+    Ok(
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Settings::from_json(&text, image_lightness)?,
+            Some("toml") => Settings::from_toml(&text, image_lightness)?,
+            Some("yaml" | "yml") => Settings::from_yaml(&text, image_lightness)?,
+            _ => bail!(
+                "Unknown settings format for {} (expected .json, .toml or .yaml)",
+                path.display()
+            ),
+        },
+    )
+}
+
+/// Writes settings to a JSON or TOML file, leaving out the settings that the style's preset
+/// fills in
+fn save_settings(path: &Path, settings: &Settings, image_lightness: f64) -> Result<()> {
+    let image_lightness = Some(image_lightness);
+    let text = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => settings.to_toml(image_lightness)?,
+        _ => settings.to_json(image_lightness)?,
+    };
+    fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Generates a palette, resolving the settings for the image first
+fn generate(
+    img: &RgbImage,
+    palette_settings: &PaletteSettings,
+    parse_colors_settings: &ParseColorsSettings,
+) -> Result<[Oklch<f64>; 24]> {
+    parse_colors_settings.validate()?;
+    let palette_settings = palette_settings.resolve(img, parse_colors_settings);
+    let generator = Base24Generator::new(palette_settings.clone());
+    or_neutral(
+        generator.generate_from_image(img, parse_colors_settings.extractor().as_ref()),
+        || neutral_palette(&palette_settings),
+    )
+}
+
+/// Generates matching dark and light palettes, falling back to neutral ones like [`generate`]
+fn generate_dark_and_light(
+    img: &RgbImage,
+    palette_settings: &PaletteSettings,
+    parse_colors_settings: &ParseColorsSettings,
+) -> Result<[[Oklch<f64>; 24]; 2]> {
+    parse_colors_settings.validate()?;
+    let palette_settings = &palette_settings.resolve(img, parse_colors_settings);
+    let colors = parse_colors_settings
+        .extractor()
+        .extract(img)
+        .map(|colors| colors.into_iter().map(|weighted| weighted.color).collect());
+    or_neutral(
+        colors.and_then(|colors| generate_pair(colors, palette_settings)),
+        || {
+            let [dark, light] = palette_settings.pair();
+            Ok([neutral_palette(&dark)?, neutral_palette(&light)?])
+        },
+    )
+}
+
+/// Replaces a failure to find any colors in the image with a neutral result
+fn or_neutral<T>(result: iro::Result<T>, neutral: impl FnOnce() -> iro::Result<T>) -> Result<T> {
+    match result {
+        Err(IroError::NoColorsFound) => {
+            eprintln!("Warning: no colors found in the image, generating a neutral scheme");
+            Ok(neutral()?)
+        }
+        result => Ok(result?),
+    }
+}
+
+impl OutputArgs {
+    /// Checks that the options can be combined
+    fn check(&self) -> Result<()> {
+        let structured =
+            !(self.show || self.apply || self.apply_script) && self.format != OutputFormat::Yaml;
+        if self.layout == Layout::Ansi16 && !structured {
+            bail!("--layout ansi16 only applies to JSON, TOML and CSS output");
+        }
+        Ok(())
+    }
+
+    /// The file extension for the output
+    fn extension(&self) -> &'static str {
+        if self.show || self.apply {
+            "txt"
+        } else if self.apply_script {
+            "sh"
+        } else {
+            match self.format {
+                OutputFormat::Yaml => "yaml",
+                OutputFormat::Json => "json",
+                OutputFormat::Toml => "toml",
+                OutputFormat::Css => "css",
+            }
+        }
+    }
+}
+
+fn emit(
+    output: &OutputArgs,
+    style: &Base24Style,
+    palette_settings: &PaletteSettings,
+    parse_colors_settings: &ParseColorsSettings,
+) -> Result<()> {
+    emit_to(
+        &mut io::stdout().lock(),
+        output,
+        style,
+        palette_settings,
+        parse_colors_settings,
+    )
+}
+
+fn emit_to(
+    out: &mut impl Write,
+    output: &OutputArgs,
+    style: &Base24Style,
+    palette_settings: &PaletteSettings,
+    parse_colors_settings: &ParseColorsSettings,
+) -> Result<()> {
+    let simulated;
+    let style = match output.simulate {
+        Some(deficiency) => {
+            simulated = Base24Style {
+                variant: format!("{} ({deficiency})", style.variant),
+                palette: simulate_palette(&style.palette, deficiency),
+                ..style.clone()
+            };
+            &simulated
+        }
+        None => style,
+    };
+    if output.gamut_report {
+        for adjustment in gamut_report(&style.palette, output.gamut) {
+            eprintln!(
+                "base{:02X}: chroma {:.3} -> {:.3} (ΔE {:.3})",
+                adjustment.slot,
+                adjustment.original.chroma,
+                adjustment.mapped.chroma,
+                adjustment.delta_e(),
+            );
+        }
+    }
+    if output.show {
+        write!(out, "{}", Preview::new(style).render())?;
+    } else if output.apply {
+        write!(out, "{}", terminal::osc_sequences(style))?;
+    } else if output.apply_script {
+        write!(out, "{}", terminal::osc_script(style))?;
+    } else {
+        let ansi_palette = ANSI_SLOTS.map(|slot| style.palette[slot]);
+        let export = match output.layout {
+            Layout::Base24 => {
+                SchemeExport::new(style, palette_settings, parse_colors_settings, output.gamut)
+            }
+            Layout::Ansi16 => SchemeExport::custom::<Ansi16Generator>(
+                &style.name,
+                &style.author,
+                &style.variant,
+                &ansi_palette,
+                parse_colors_settings,
+                output.gamut,
+            ),
+        };
+        match output.format {
+            OutputFormat::Yaml => writeln!(out, "{}", serde_yaml::to_string(style)?)?,
+            OutputFormat::Json => writeln!(out, "{}", export.to_json()?)?,
+            OutputFormat::Toml => writeln!(out, "{}", export.to_toml()?)?,
+            OutputFormat::Css => write!(out, "{}", export.to_css())?,
+        }
+    }
+    Ok(())
+}
+
+fn load_scheme(path: &Path) -> Result<Base24Style> {
+    let scheme =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(serde_yaml::from_str(&scheme)?)
+}
+
+fn audit_scheme(scheme_path: &Path, metric: AuditMetric, json: bool) -> Result<()> {
+    let report = audit(&load_scheme(scheme_path)?, metric);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for check in &report.checks {
+            println!(
+                "{}  {} on {}  WCAG {:>5.2} (min {:.1})  APCA {:>5.1} (min {:.0})  {}",
+                if check.passes { "PASS" } else { "FAIL" },
+                check.foreground,
+                check.background,
+                check.wcag,
+                check.requirement.min_wcag(),
+                check.apca,
+                check.requirement.min_apca(),
+                check.role,
+            );
         }
     }
+    let failures = report.failures().count();
+    if failures > 0 {
+        bail!(
+            "{failures} of {} contrast checks failed ({})",
+            report.checks.len(),
+            report.metric
+        );
+    }
+    Ok(())
 }
 
-impl From<Args> for ParseColorsSettings {
-    fn from(args: Args) -> Self {
-        Self {
-            segment_size: args.segment_size,
+fn cvd_report(scheme_path: &Path, json: bool) -> Result<()> {
+    let style = load_scheme(scheme_path)?;
+    let report = Deficiency::ALL
+        .iter()
+        .map(|deficiency| {
+            (
+                deficiency.to_string(),
+                highlight_distances(&style.palette, *deficiency),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    for (deficiency, distances) in report {
+        println!("{deficiency}:");
+        for distance in distances.iter().take(3) {
+            println!(
+                "  base{:02X} / base{:02X}  ΔE {:.3}",
+                distance.a, distance.b, distance.delta_e
+            );
         }
     }
+    Ok(())
+}
+
+fn regenerate(scheme_path: &Path, image_path: Option<&Path>, output: &OutputArgs) -> Result<()> {
+    let mut style = load_scheme(scheme_path)?;
+    let Some(provenance) = style.provenance.clone() else {
+        bail!(
+            "{} has no provenance; generate it with --provenance",
+            scheme_path.display()
+        );
+    };
+    let image_path = match (image_path, &provenance.source_image) {
+        (Some(path), _) => path.to_path_buf(),
+        (None, Some(path)) => PathBuf::from(path),
+        (None, None) => bail!("The scheme does not record its source image; pass --image"),
+    };
+    let image_bytes = fs::read(&image_path)
+        .with_context(|| format!("Failed to read {}", image_path.display()))?;
+    if !provenance.matches(&image_bytes) {
+        eprintln!(
+            "Warning: {} does not match the image the scheme was generated from",
+            image_path.display()
+        );
+    }
+    if provenance.iro_version != iro::VERSION {
+        eprintln!(
+            "Warning: the scheme was generated with iro {} (this is {})",
+            provenance.iro_version,
+            iro::VERSION
+        );
+    }
+    let img = decode_image(&image_bytes)?;
+    style.palette = match &provenance.pair_settings {
+        Some(pair_settings) => {
+            let [dark, light] =
+                generate_dark_and_light(&img, pair_settings, &provenance.parse_colors_settings)?;
+            match provenance.palette_settings.style.is_light() {
+                true => light,
+                false => dark,
+            }
+        }
+        None => generate(
+            &img,
+            &provenance.palette_settings,
+            &provenance.parse_colors_settings,
+        )?,
+    };
+    emit(
+        output,
+        &style,
+        &provenance.palette_settings,
+        &provenance.parse_colors_settings,
+    )
 }
 
 pub fn main() -> Result<()> {
     let args = Args::try_parse()?;
-    let mut img = image::open(&args.path)?.into_rgb8();
-    let settings = args.clone().into();
-    let colors = generate_palette(parse_colors(&mut img, &args.clone().into()), &settings)?;
-    let style = Base24Style {
+    if let Some(Command::Regenerate { output, .. }) = &args.command {
+        output.check()?;
+    }
+    args.output.check()?;
+    match &args.command {
+        Some(Command::Regenerate {
+            scheme,
+            image,
+            output,
+        }) => return regenerate(scheme, image.as_deref(), output),
+        Some(Command::Audit {
+            scheme,
+            metric,
+            json,
+        }) => return audit_scheme(scheme, *metric, *json),
+        Some(Command::Cvd { scheme, json }) => return cvd_report(scheme, *json),
+        None => {}
+    }
+    let path = args
+        .path
+        .clone()
+        .expect("path is required without a subcommand");
+    let image_bytes =
+        fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let img = decode_image(&image_bytes)?;
+    let image_lightness = mean_lightness(&img);
+    let (settings, parse_colors_settings) = match &args.settings {
+        Some(settings_path) => {
+            let Settings {
+                palette,
+                parse_colors,
+            } = settings_from_file(settings_path, image_lightness)?;
+            (palette, parse_colors)
+        }
+        None => settings_from_args(args.clone(), image_lightness)?,
+    };
+    if let Some(settings_path) = &args.save_settings {
+        let settings = Settings {
+            palette: settings.clone(),
+            parse_colors: parse_colors_settings.clone(),
+        };
+        save_settings(settings_path, &settings, image_lightness)?;
+        eprintln!("Wrote {}", settings_path.display());
+    }
+    let source_image = path.canonicalize().unwrap_or(path).display().to_string();
+    // The settings of a pair, from which both of its sides are derived
+    let pair_settings = &settings;
+    let make_style = |settings: &PaletteSettings, palette, paired: bool| Base24Style {
         name: "Iro Theme".to_string(),
         author: "You".to_string(),
-        variant: settings.style.to_string(),
-        palette: colors,
+        variant: settings.style.resolve(image_lightness).to_string(),
+        palette,
+        provenance: args
+            .provenance
+            .then(|| {
+                Provenance::new(
+                    &image_bytes,
+                    Some(source_image.clone()),
+                    settings.clone(),
+                    parse_colors_settings.clone(),
+                )
+            })
+            .map(|provenance| match paired {
+                true => provenance.with_pair(pair_settings.clone()),
+                false => provenance,
+            }),
     };
-    println!("{}", serde_yaml::to_string(&style)?);
-    Ok(())
+
+    if let Some(prefix) = &args.pair {
+        let palettes = generate_dark_and_light(&img, &settings, &parse_colors_settings)?;
+        let sides = settings.resolve(&img, &parse_colors_settings).pair();
+        for (settings, palette) in iter::zip(sides, palettes) {
+            let variant = if settings.style.is_light() {
+                "light"
+            } else {
+                "dark"
+            };
+            let path = format!("{prefix}-{variant}.{}", args.output.extension());
+            let mut file =
+                File::create(&path).with_context(|| format!("Failed to create {path}"))?;
+            let style = make_style(&settings, palette, true);
+            emit_to(
+                &mut file,
+                &args.output,
+                &style,
+                &settings,
+                &parse_colors_settings,
+            )?;
+            eprintln!("Wrote {path}");
+        }
+        return Ok(());
+    }
+
+    let colors = generate(&img, &settings, &parse_colors_settings)?;
+    let style = make_style(&settings, colors, false);
+    emit(&args.output, &style, &settings, &parse_colors_settings)
 }
@@ -0,0 +1,193 @@
+use iro::{lch_to_hex, lch_to_rgb, Base24Style, Oklch};
+
+use std::fmt::Write;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+
+fn fg(color: &Oklch<f64>) -> String {
+    let [r, g, b] = lch_to_rgb(color);
+    format!("\x1b[38;2;{r};{g};{b}m")
+}
+
+fn bg(color: &Oklch<f64>) -> String {
+    let [r, g, b] = lch_to_rgb(color);
+    format!("\x1b[48;2;{r};{g};{b}m")
+}
+
+/// Renders a palette preview using 24-bit ANSI escape sequences
+pub struct Preview<'a> {
+    palette: &'a [Oklch<f64>; 24],
+}
+
+impl<'a> Preview<'a> {
+    pub fn new(style: &'a Base24Style) -> Self {
+        Self {
+            palette: &style.palette,
+        }
+    }
+
+    /// Returns the escape sequence that sets the foreground to `slot` on the default background
+    fn paint(&self, slot: usize) -> String {
+        format!("{}{}", bg(&self.palette[0x00]), fg(&self.palette[slot]))
+    }
+
+    /// Writes `lines` of (slot, text) spans on the default background
+    fn write_block(&self, out: &mut String, lines: &[&[(usize, &str)]]) {
+        let width = lines
+            .iter()
+            .map(|spans| {
+                spans
+                    .iter()
+                    .map(|(_, text)| text.chars().count())
+                    .sum::<usize>()
+            })
+            .max()
+            .unwrap_or(0);
+        for spans in lines {
+            let mut len = 0;
+            out.push_str(&self.paint(0x05));
+            out.push_str("  ");
+            for (slot, text) in spans.iter() {
+                out.push_str(&self.paint(*slot));
+                out.push_str(text);
+                len += text.chars().count();
+            }
+            out.push_str(&self.paint(0x05));
+            out.push_str(&" ".repeat(width - len + 2));
+            out.push_str(RESET);
+            out.push('\n');
+        }
+    }
+
+    fn write_swatches(&self, out: &mut String) {
+        for (row, colors) in self.palette.chunks(8).enumerate() {
+            for (col, color) in colors.iter().enumerate() {
+                let idx = row * 8 + col;
+                let _ = write!(out, "{} base{idx:02X} {RESET} ", bg(color));
+            }
+            out.push('\n');
+        }
+    }
+
+    fn write_values(&self, out: &mut String) {
+        for (idx, color) in self.palette.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "{}    {RESET} base{idx:02X}  #{}  oklch({:.3} {:.3} {:.1})",
+                bg(color),
+                lch_to_hex(color),
+                color.l,
+                color.chroma,
+                color.hue.into_positive_degrees(),
+            );
+        }
+    }
+
+    fn write_ls(&self, out: &mut String) {
+        self.write_block(
+            out,
+            &[
+                &[(0x03, "$ "), (0x05, "ls -F")],
+                &[
+                    (0x0D, "assets/"),
+                    (0x05, "  "),
+                    (0x0B, "build.sh*"),
+                    (0x05, "  "),
+                    (0x05, "Cargo.toml"),
+                    (0x05, "  "),
+                    (0x0C, "latest@"),
+                ],
+                &[
+                    (0x0D, "src/"),
+                    (0x05, "     "),
+                    (0x08, "dist.tar.gz"),
+                    (0x05, "  "),
+                    (0x0E, "logo.png"),
+                    (0x05, "    "),
+                    (0x04, ".gitignore"),
+                ],
+            ],
+        );
+    }
+
+    fn write_diff(&self, out: &mut String) {
+        self.write_block(
+            out,
+            &[
+                &[(0x03, "$ "), (0x05, "git diff")],
+                &[(0x05, "diff --git a/src/lib.rs b/src/lib.rs")],
+                &[(0x08, "--- a/src/lib.rs")],
+                &[(0x0B, "+++ b/src/lib.rs")],
+                &[(0x0C, "@@ -1,3 +1,3 @@")],
+                &[(0x05, " fn main() {")],
+                &[(0x08, "-    println!(\"hello\");")],
+                &[(0x0B, "+    println!(\"hello, world\");")],
+                &[(0x05, " }")],
+            ],
+        );
+    }
+
+    fn write_code(&self, out: &mut String) {
+        self.write_block(
+            out,
+            &[
+                &[(0x03, "// Counts the colors in a palette")],
+                &[
+                    (0x0E, "pub fn "),
+                    (0x0D, "count"),
+                    (0x05, "(palette: &"),
+                    (0x0A, "Palette"),
+                    (0x05, ") -> "),
+                    (0x0A, "usize"),
+                    (0x05, " {"),
+                ],
+                &[
+                    (0x0E, "    let "),
+                    (0x05, "name = "),
+                    (0x0B, "\"iro\""),
+                    (0x05, ";"),
+                ],
+                &[
+                    (0x0E, "    if "),
+                    (0x05, "palette.len() > "),
+                    (0x09, "24"),
+                    (0x05, " {"),
+                ],
+                &[
+                    (0x08, "        panic!"),
+                    (0x05, "("),
+                    (0x0B, "\"too many colors\""),
+                    (0x05, ");"),
+                ],
+                &[(0x05, "    }")],
+                &[
+                    (0x05, "    palette."),
+                    (0x0D, "len"),
+                    (0x05, "() "),
+                    (0x0C, "+"),
+                    (0x05, " name."),
+                    (0x0D, "len"),
+                    (0x05, "()"),
+                ],
+                &[(0x05, "}")],
+            ],
+        );
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (title, section) in [
+            ("Swatches", Self::write_swatches as fn(&Self, &mut String)),
+            ("Files", Self::write_ls),
+            ("Diff", Self::write_diff),
+            ("Code", Self::write_code),
+            ("Values", Self::write_values),
+        ] {
+            let _ = writeln!(out, "{BOLD}{title}{RESET}");
+            section(self, &mut out);
+            out.push('\n');
+        }
+        out
+    }
+}
@@ -0,0 +1,45 @@
+use iro::{lch_to_rgb, Base24Style, Oklch};
+
+use std::fmt::Write;
+
+/// Formats a color as an X11 color specification (`rgb:rr/gg/bb`)
+fn x11_color(color: &Oklch<f64>) -> String {
+    let [r, g, b] = lch_to_rgb(color);
+    format!("rgb:{r:02x}/{g:02x}/{b:02x}")
+}
+
+/// The OSC sequence bodies that recolor a terminal: ANSI colors (OSC 4), foreground (OSC 10),
+/// background (OSC 11) and cursor (OSC 12)
+fn osc_commands(style: &Base24Style) -> Vec<String> {
+    let ansi = style
+        .ansi_colors()
+        .iter()
+        .enumerate()
+        .map(|(idx, color)| format!("4;{idx};{}", x11_color(color)))
+        .collect::<Vec<_>>();
+    ansi.into_iter()
+        .chain([
+            format!("10;{}", x11_color(&style.foreground())),
+            format!("11;{}", x11_color(&style.background())),
+            format!("12;{}", x11_color(&style.cursor())),
+        ])
+        .collect()
+}
+
+/// Escape sequences that apply the style to the current terminal
+pub fn osc_sequences(style: &Base24Style) -> String {
+    osc_commands(style)
+        .iter()
+        .map(|command| format!("\x1b]{command}\x1b\\"))
+        .collect()
+}
+
+/// A shell script that applies the style to the terminal it is run in
+pub fn osc_script(style: &Base24Style) -> String {
+    let mut script = String::from("#!/bin/sh\n");
+    let _ = writeln!(script, "# {} ({})", style.name, style.variant);
+    for command in osc_commands(style) {
+        let _ = writeln!(script, "printf '\\033]{command}\\033\\\\'");
+    }
+    script
+}
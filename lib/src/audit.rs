@@ -0,0 +1,162 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Display;
+
+use crate::base24::{Base24Style, HIGHLIGHT_SLOTS};
+use crate::contrast::{apca_contrast, wcag_contrast};
+
+/// How legible a pairing needs to be
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Requirement {
+    /// Body text, code and highlights (WCAG AA 4.5:1, APCA Lc 60)
+    Text,
+    /// Comments and other de-emphasized text (WCAG 3:1, APCA Lc 45)
+    Secondary,
+}
+
+impl Requirement {
+    pub fn min_wcag(self) -> f64 {
+        match self {
+            Self::Text => 4.5,
+            Self::Secondary => 3.0,
+        }
+    }
+
+    pub fn min_apca(self) -> f64 {
+        match self {
+            Self::Text => 60.0,
+            Self::Secondary => 45.0,
+        }
+    }
+}
+
+/// Which contrast measurements decide whether a check passes
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditMetric {
+    /// The WCAG 2.x contrast ratio
+    #[default]
+    Wcag,
+    /// APCA lightness contrast
+    Apca,
+    /// Both WCAG and APCA (stricter than either)
+    Both,
+}
+
+impl Display for AuditMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Wcag => "wcag",
+                Self::Apca => "apca",
+                Self::Both => "both",
+            }
+        )
+    }
+}
+
+/// Foreground-on-background pairs from the Base24 styling guide
+/// See: https://github.com/tinted-theming/base24/blob/18af13d81e31a37be3617891c0a9e7a87da0ade9/styling.md
+const PAIRS: &[(&str, usize, usize, Requirement)] = &[
+    ("Default text", 0x05, 0x00, Requirement::Text),
+    ("Text on lighter background", 0x05, 0x01, Requirement::Text),
+    ("Selected text", 0x05, 0x02, Requirement::Text),
+    ("Light foreground", 0x06, 0x00, Requirement::Text),
+    ("Status bar text", 0x04, 0x01, Requirement::Secondary),
+    ("Comments", 0x03, 0x00, Requirement::Secondary),
+    (
+        "Line highlight comments",
+        0x03,
+        0x01,
+        Requirement::Secondary,
+    ),
+];
+
+const HIGHLIGHT_BACKGROUNDS: &[(&str, usize, Requirement)] = &[
+    ("Highlight", 0x00, Requirement::Text),
+    ("Highlight on lighter background", 0x01, Requirement::Text),
+    ("Highlight in selection", 0x02, Requirement::Secondary),
+];
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ContrastCheck {
+    pub role: String,
+    pub foreground: String,
+    pub background: String,
+    pub requirement: Requirement,
+    pub wcag: f64,
+    /// Absolute APCA lightness contrast (Lc)
+    pub apca: f64,
+    pub passes_wcag: bool,
+    pub passes_apca: bool,
+    pub passes: bool,
+}
+
+impl ContrastCheck {
+    fn new(
+        style: &Base24Style,
+        role: &str,
+        fg: usize,
+        bg: usize,
+        requirement: Requirement,
+        metric: AuditMetric,
+    ) -> Self {
+        let (fg_color, bg_color) = (&style.palette[fg], &style.palette[bg]);
+        let wcag = wcag_contrast(fg_color, bg_color);
+        let apca = apca_contrast(fg_color, bg_color).abs();
+        let passes_wcag = wcag >= requirement.min_wcag();
+        let passes_apca = apca >= requirement.min_apca();
+        Self {
+            role: role.to_string(),
+            foreground: format!("base{fg:02X}"),
+            background: format!("base{bg:02X}"),
+            requirement,
+            wcag,
+            apca,
+            passes_wcag,
+            passes_apca,
+            passes: match metric {
+                AuditMetric::Wcag => passes_wcag,
+                AuditMetric::Apca => passes_apca,
+                AuditMetric::Both => passes_wcag && passes_apca,
+            },
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AuditReport {
+    pub metric: AuditMetric,
+    pub passes: bool,
+    pub checks: Vec<ContrastCheck>,
+}
+
+impl AuditReport {
+    pub fn failures(&self) -> impl Iterator<Item = &ContrastCheck> {
+        self.checks.iter().filter(|check| !check.passes)
+    }
+}
+
+/// Checks the contrast of every foreground-on-background pair used by Base24 styling,
+/// passing or failing each pair on `metric`
+pub fn audit(style: &Base24Style, metric: AuditMetric) -> AuditReport {
+    let pairs = PAIRS.iter().copied();
+    let highlights = HIGHLIGHT_BACKGROUNDS
+        .iter()
+        .flat_map(|&(role, bg, requirement)| HIGHLIGHT_SLOTS.map(|fg| (role, fg, bg, requirement)));
+    let checks = pairs
+        .chain(highlights)
+        .map(|(role, fg, bg, requirement)| {
+            ContrastCheck::new(style, role, fg, bg, requirement, metric)
+        })
+        .collect::<Vec<_>>();
+    AuditReport {
+        metric,
+        passes: checks.iter().all(|check| check.passes),
+        checks,
+    }
+}
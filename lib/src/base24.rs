@@ -1,22 +1,85 @@
-use anyhow::{Context, Result};
-use itertools::{Either, Itertools};
+use clap::ValueEnum;
+use image::RgbImage;
+use itertools::Itertools;
 use palette::Oklch;
+use serde::de::Error as _;
 use serde::ser::SerializeMap;
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::mem::MaybeUninit;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
 use std::{array, iter};
 
-use crate::lch_to_hex;
+use crate::contrast::{enforce_contrast, ContrastMetric};
+use crate::cvd::nudge_hues;
+use crate::error::{check_range, IroError, Result};
+use crate::highlights::{BrightDerivation, HighlightLightness};
+use crate::hues::{assign_semantic, fill_hues, separate_hues, HueAssignment, HueFill};
+use crate::provenance::Provenance;
+use crate::ramp::LightnessRamp;
+use crate::settings::PaletteSettingsBuilder;
+use crate::{background_hue, hex_to_lch, lch_to_hex, mean_lightness, ParseColorsSettings};
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Base24Style {
     pub name: String,
     pub author: String,
     pub variant: String,
-    #[serde(serialize_with = "serialize_colors")]
+    #[serde(
+        serialize_with = "serialize_colors",
+        deserialize_with = "deserialize_colors"
+    )]
     pub palette: [Oklch<f64>; 24],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// The Base24 slots used for the 16 ANSI terminal colors, in ANSI order
+/// See: https://github.com/tinted-theming/base24/blob/18af13d81e31a37be3617891c0a9e7a87da0ade9/styling.md
+pub const ANSI_SLOTS: [usize; 16] = [
+    0x00, 0x08, 0x0B, 0x09, 0x0D, 0x0E, 0x0C, 0x05, // normal
+    0x02, 0x12, 0x14, 0x13, 0x16, 0x17, 0x15, 0x07, // bright
+];
+
+/// Slots used for text on the default background
+pub const FOREGROUND_SLOTS: [usize; 4] = [0x04, 0x05, 0x06, 0x07];
+
+/// Slots used for highlighted text (syntax, terminal colors) on the default background
+pub const HIGHLIGHT_SLOTS: [usize; 14] = [
+    0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, // normal
+    0x12, 0x13, 0x14, 0x15, 0x16, 0x17, // bright
+];
+
+/// Bright highlight slots and the highlight slots they are derived from
+pub const BRIGHT_SOURCES: [(usize, usize); 6] = [
+    (0x12, 0x08),
+    (0x13, 0x09),
+    (0x14, 0x0B),
+    (0x15, 0x0C),
+    (0x16, 0x0D),
+    (0x17, 0x0E),
+];
+
+impl Base24Style {
+    /// The 16 ANSI terminal colors
+    pub fn ansi_colors(&self) -> [Oklch<f64>; 16] {
+        ANSI_SLOTS.map(|slot| self.palette[slot])
+    }
+
+    pub fn foreground(&self) -> Oklch<f64> {
+        self.palette[0x05]
+    }
+
+    pub fn background(&self) -> Oklch<f64> {
+        self.palette[0x00]
+    }
+
+    pub fn cursor(&self) -> Oklch<f64> {
+        self.palette[0x05]
+    }
 }
 
 fn serialize_colors<S>(colors: &[Oklch<f64>; 24], serializer: S) -> Result<S::Ok, S::Error>
@@ -32,6 +95,60 @@ where
     serializer.end()
 }
 
+fn deserialize_colors<'de, D>(deserializer: D) -> Result<[Oklch<f64>; 24], D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hexes = BTreeMap::<String, String>::deserialize(deserializer)?;
+    let colors = (0..24)
+        .map(|idx| {
+            let key = format!("base{idx:02X}");
+            let hex = hexes
+                .get(&key)
+                .ok_or_else(|| D::Error::custom(format!("missing color {key}")))?;
+            hex_to_lch(hex).map_err(D::Error::custom)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    color_array::<24>(colors.into_iter()).ok_or_else(|| D::Error::custom("not enough colors"))
+}
+
+/// Parses a slot name like `base08` into its index in the palette
+pub fn parse_slot(name: &str) -> Result<usize> {
+    name.strip_prefix("base")
+        .and_then(|index| usize::from_str_radix(index, 16).ok())
+        .filter(|&slot| slot < 24)
+        .ok_or_else(|| {
+            IroError::InvalidSettings(format!("invalid slot {name:?} (expected base00 - base17)"))
+        })
+}
+
+fn serialize_overrides<S>(
+    overrides: &BTreeMap<usize, Oklch<f64>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut serializer = serializer.serialize_map(Some(overrides.len()))?;
+
+    for (slot, color) in overrides {
+        serializer.serialize_entry(&format_args!("base{slot:02X}"), &lch_to_hex(color))?;
+    }
+
+    serializer.end()
+}
+
+fn deserialize_overrides<'de, D>(deserializer: D) -> Result<BTreeMap<usize, Oklch<f64>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    BTreeMap::<String, String>::deserialize(deserializer)?
+        .iter()
+        .map(|(slot, hex)| Ok((parse_slot(slot)?, hex_to_lch(hex)?)))
+        .collect::<Result<_>>()
+        .map_err(D::Error::custom)
+}
+
 pub fn color_array<const N: usize>(
     iter: impl Iterator<Item = Oklch<f64>>,
 ) -> Option<[Oklch<f64>; N]> {
@@ -52,15 +169,33 @@ pub fn color_array<const N: usize>(
     Some(colors.map(|color| unsafe { color.assume_init() }))
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
 pub enum PaletteStyle {
+    #[default]
     Dark,
     Light,
+    /// A dark style with a raised background and softer foreground
+    Dim,
+    /// A dark style with pure black and white extremes and stronger highlights
+    HighContrast,
+    /// Dark or light, depending on the lightness of the source image
+    Auto,
 }
 
-impl Default for PaletteStyle {
-    fn default() -> Self {
-        Self::Dark
+impl PaletteStyle {
+    /// Whether the background is lighter than the foreground
+    pub fn is_light(self) -> bool {
+        self == Self::Light
+    }
+
+    /// Chooses dark or light for `Auto` from the mean lightness (0-1) of the source image
+    pub fn resolve(self, mean_lightness: f64) -> Self {
+        match self {
+            Self::Auto if mean_lightness >= 0.5 => Self::Light,
+            Self::Auto => Self::Dark,
+            style => style,
+        }
     }
 }
 
@@ -72,41 +207,317 @@ impl Display for PaletteStyle {
             match self {
                 Self::Dark => "dark",
                 Self::Light => "light",
+                Self::Dim => "dim",
+                Self::HighContrast => "high-contrast",
+                Self::Auto => "auto",
             }
         )
     }
 }
 
+/// Where the hue of the base colors and backgrounds comes from
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundTint {
+    /// The hue of the most common extracted color
+    #[default]
+    Accent,
+    /// A specific hue, in degrees
+    Hue(f64),
+    /// No tint at all
+    Neutral,
+    /// The hue of the source image's background tone, resolved for each image
+    Image,
+}
+
+impl Display for BackgroundTint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Accent => write!(f, "accent"),
+            Self::Hue(hue) => write!(f, "{hue}"),
+            Self::Neutral => write!(f, "neutral"),
+            Self::Image => write!(f, "image"),
+        }
+    }
+}
+
+impl FromStr for BackgroundTint {
+    type Err = IroError;
+
+    /// Parses `accent`, `neutral`, `image` or a hue in degrees
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "accent" => Self::Accent,
+            "neutral" => Self::Neutral,
+            "image" => Self::Image,
+            hue => Self::Hue(hue.parse().map_err(|_| {
+                IroError::InvalidSettings(format!(
+                    "expected accent, neutral, image or a hue, got {hue:?}"
+                ))
+            })?),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct PaletteSettings {
     pub style: PaletteStyle,
     pub keep: usize,
     pub rotation: usize,
+    /// How extracted hues are assigned to highlight slots
+    #[serde(default)]
+    pub hue_assignment: HueAssignment,
+    /// How highlight hues are filled in when fewer than eight colors are kept
+    #[serde(default)]
+    pub hue_fill: HueFill,
     pub base_chroma: f64,
+    /// The hue of the base colors and backgrounds
+    #[serde(default)]
+    pub background_tint: BackgroundTint,
+    /// The lightness ramp of base00 - base07
+    #[serde(default)]
+    pub ramp: LightnessRamp,
     pub hl_lightness: f64,
+    /// How each highlight's lightness is derived from `hl_lightness`
+    #[serde(default)]
+    pub hl_lightness_mode: HighlightLightness,
+    /// How far source lightness may stray from `hl_lightness` in source mode
+    #[serde(default = "default_hl_lightness_spread")]
+    pub hl_lightness_spread: f64,
     pub hl_chroma: f64,
+    /// The minimum Oklch hue difference (in degrees) between highlights
+    #[serde(default)]
+    pub min_hue_separation: Option<f64>,
+    /// The minimum Oklab ΔE between highlights
+    #[serde(default)]
+    pub min_delta_e: Option<f64>,
+    /// How much highlights keep the lightness and chroma of the extracted colors
+    /// (0 = normalized, 1 = as extracted)
+    #[serde(default)]
+    pub fidelity: f64,
+    /// How the bright highlights are derived from the highlights
+    #[serde(default)]
+    pub brights: BrightDerivation,
+    /// The chroma of the bright highlights, instead of deriving it
+    #[serde(default)]
+    pub bright_chroma: Option<f64>,
+    /// The minimum contrast of foreground and highlight colors against the background
+    pub min_contrast: Option<f64>,
+    /// How contrast is measured for `min_contrast`
+    #[serde(default)]
+    pub contrast_metric: ContrastMetric,
+    /// The minimum Oklab ΔE between highlights under simulated color-vision deficiencies
+    pub cvd_min_delta_e: Option<f64>,
+    /// Colors that replace the generated ones in specific slots
+    #[serde(
+        default,
+        skip_serializing_if = "BTreeMap::is_empty",
+        serialize_with = "serialize_overrides",
+        deserialize_with = "deserialize_overrides"
+    )]
+    pub overrides: BTreeMap<usize, Oklch<f64>>,
+}
+
+fn default_hl_lightness_spread() -> f64 {
+    0.1
 }
 
 impl PaletteSettings {
+    /// The valid number of colors to keep from the image
+    pub const KEEP: RangeInclusive<usize> = 1..=8;
+    /// The valid range of lightnesses
+    pub const LIGHTNESS: RangeInclusive<f64> = 0.0..=1.0;
+    /// The valid range of chromas (no sRGB color has a chroma above ~0.32)
+    pub const CHROMA: RangeInclusive<f64> = 0.0..=0.4;
+    /// The valid range of settings that blend between two behaviors
+    pub const FRACTION: RangeInclusive<f64> = 0.0..=1.0;
+    /// The valid range of hue separations, in degrees
+    pub const HUE_SEPARATION: RangeInclusive<f64> = 0.0..=180.0;
+    /// The valid range of Oklab ΔE separations
+    pub const DELTA_E: RangeInclusive<f64> = 0.0..=1.0;
+
     pub fn default_for(style: PaletteStyle) -> Self {
         match style {
             PaletteStyle::Dark => Self {
                 style: PaletteStyle::Dark,
                 keep: 8,
                 rotation: 0,
+                hue_assignment: HueAssignment::Rotation,
+                hue_fill: HueFill::Repeat,
                 base_chroma: 0.03,
+                background_tint: BackgroundTint::Accent,
+                ramp: LightnessRamp::default_for(PaletteStyle::Dark),
                 hl_chroma: 0.12,
+                min_hue_separation: None,
+                min_delta_e: None,
+                fidelity: 0.0,
+                brights: BrightDerivation::default_for(PaletteStyle::Dark),
+                bright_chroma: None,
                 hl_lightness: 0.6,
+                hl_lightness_mode: HighlightLightness::Fixed,
+                hl_lightness_spread: default_hl_lightness_spread(),
+                min_contrast: None,
+                contrast_metric: ContrastMetric::Wcag,
+                cvd_min_delta_e: None,
+                overrides: BTreeMap::new(),
             },
             PaletteStyle::Light => Self {
                 style: PaletteStyle::Light,
                 keep: 8,
                 rotation: 0,
+                hue_assignment: HueAssignment::Rotation,
+                hue_fill: HueFill::Repeat,
                 base_chroma: 0.04,
+                background_tint: BackgroundTint::Accent,
+                ramp: LightnessRamp::default_for(PaletteStyle::Light),
                 hl_chroma: 0.14,
+                min_hue_separation: None,
+                min_delta_e: None,
+                fidelity: 0.0,
+                brights: BrightDerivation::default_for(PaletteStyle::Light),
+                bright_chroma: None,
                 hl_lightness: 0.5,
+                hl_lightness_mode: HighlightLightness::Fixed,
+                hl_lightness_spread: default_hl_lightness_spread(),
+                min_contrast: None,
+                contrast_metric: ContrastMetric::Wcag,
+                cvd_min_delta_e: None,
+                overrides: BTreeMap::new(),
+            },
+            PaletteStyle::Dim => Self {
+                style: PaletteStyle::Dim,
+                base_chroma: 0.025,
+                ramp: LightnessRamp::default_for(PaletteStyle::Dim),
+                hl_chroma: 0.1,
+                hl_lightness: 0.65,
+                ..Self::default_for(PaletteStyle::Dark)
+            },
+            PaletteStyle::HighContrast => Self {
+                style: PaletteStyle::HighContrast,
+                base_chroma: 0.02,
+                ramp: LightnessRamp::default_for(PaletteStyle::HighContrast),
+                hl_chroma: 0.16,
+                hl_lightness: 0.7,
+                min_contrast: Some(7.0),
+                ..Self::default_for(PaletteStyle::Dark)
+            },
+            // Until it is resolved, an auto style uses the dark preset
+            PaletteStyle::Auto => Self {
+                style: PaletteStyle::Auto,
+                ..Self::default_for(PaletteStyle::Dark)
             },
         }
     }
+
+    /// The settings for a specific image, with an auto style and an image background tint
+    /// resolved. Settings files and URLs keep them unresolved so that they can be reused
+    /// with other images.
+    pub fn resolve(&self, image: &RgbImage, parse_colors_settings: &ParseColorsSettings) -> Self {
+        Self {
+            style: self.style.resolve(mean_lightness(image)),
+            background_tint: match self.background_tint {
+                BackgroundTint::Image => background_hue(image, parse_colors_settings)
+                    .map_or(BackgroundTint::Neutral, BackgroundTint::Hue),
+                tint => tint,
+            },
+            ..self.clone()
+        }
+    }
+
+    /// A builder that starts from the preset of `style`
+    pub fn builder(style: PaletteStyle) -> PaletteSettingsBuilder {
+        PaletteSettingsBuilder::new(style)
+    }
+
+    /// Checks that every setting is within its valid range
+    pub fn validate(&self) -> Result<()> {
+        check_range("keep", self.keep, &Self::KEEP)?;
+        check_range("base_chroma", self.base_chroma, &Self::CHROMA)?;
+        if let BackgroundTint::Hue(hue) = self.background_tint {
+            check_range("background hue", hue, &(0.0..=360.0))?;
+        }
+        self.ramp.validate()?;
+        check_range("hl_lightness", self.hl_lightness, &Self::LIGHTNESS)?;
+        check_range(
+            "hl_lightness_spread",
+            self.hl_lightness_spread,
+            &Self::LIGHTNESS,
+        )?;
+        check_range("hl_chroma", self.hl_chroma, &Self::CHROMA)?;
+        if let Some(min_hue_separation) = self.min_hue_separation {
+            check_range(
+                "min_hue_separation",
+                min_hue_separation,
+                &Self::HUE_SEPARATION,
+            )?;
+        }
+        if let Some(min_delta_e) = self.min_delta_e {
+            check_range("min_delta_e", min_delta_e, &Self::DELTA_E)?;
+        }
+        check_range("fidelity", self.fidelity, &Self::FRACTION)?;
+        self.brights.validate()?;
+        if let Some(bright_chroma) = self.bright_chroma {
+            check_range("bright_chroma", bright_chroma, &Self::CHROMA)?;
+        }
+        if let Some(min_contrast) = self.min_contrast {
+            check_range("min_contrast", min_contrast, &self.contrast_metric.range())?;
+        }
+        if let Some(cvd_min_delta_e) = self.cvd_min_delta_e {
+            check_range("cvd_min_delta_e", cvd_min_delta_e, &Self::DELTA_E)?;
+        }
+        if let Some(slot) = self.overrides.keys().find(|&&slot| slot >= 24) {
+            return Err(IroError::InvalidSettings(format!(
+                "invalid override slot {slot} (expected 0 - 23)"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Dark and light settings that share this style's hue assignment and constraints.
+    /// The side matching this style keeps all of its settings, while the other side starts
+    /// from its default preset (overrides are not carried over).
+    pub fn pair(&self) -> [Self; 2] {
+        let this = match self.style {
+            PaletteStyle::Auto => Self {
+                style: PaletteStyle::Dark,
+                ..self.clone()
+            },
+            _ => self.clone(),
+        };
+        let other_style = match this.style.is_light() {
+            true => PaletteStyle::Dark,
+            false => PaletteStyle::Light,
+        };
+        let defaults = Self::default_for(other_style);
+        let other = Self {
+            style: other_style,
+            keep: this.keep,
+            rotation: this.rotation,
+            hue_assignment: this.hue_assignment,
+            hue_fill: this.hue_fill,
+            background_tint: this.background_tint,
+            hl_lightness_mode: this.hl_lightness_mode,
+            hl_lightness_spread: this.hl_lightness_spread,
+            min_hue_separation: this.min_hue_separation,
+            min_delta_e: this.min_delta_e,
+            fidelity: this.fidelity,
+            bright_chroma: this.bright_chroma,
+            ramp: LightnessRamp {
+                easing: this.ramp.easing.clone(),
+                chroma_taper: this.ramp.chroma_taper,
+                ..defaults.ramp
+            },
+            min_contrast: this.min_contrast,
+            contrast_metric: this.contrast_metric,
+            cvd_min_delta_e: this.cvd_min_delta_e,
+            ..defaults
+        };
+        match this.style.is_light() {
+            true => [other, this],
+            false => [this, other],
+        }
+    }
 }
 
 impl Default for PaletteSettings {
@@ -115,67 +526,200 @@ impl Default for PaletteSettings {
     }
 }
 
+/// Generates a dark and a light palette from the same colors, using the settings from
+/// [`PaletteSettings::pair`] so that every highlight slot gets the same hue in both
+pub fn generate_pair(
+    colors: Vec<Oklch<f64>>,
+    settings: &PaletteSettings,
+) -> Result<[[Oklch<f64>; 24]; 2]> {
+    let [dark, light] = settings.pair();
+    // Hue separation and CVD nudging depend on lightness, so the hues are settled once on
+    // the side matching the requested style and then imposed on both palettes
+    let reference = match settings.style.is_light() {
+        true => &light,
+        false => &dark,
+    };
+    let hues = generate_palette(
+        colors.clone(),
+        &PaletteSettings {
+            overrides: Default::default(),
+            ..reference.clone()
+        },
+    )?;
+    Ok([
+        generate_palette_with_hues(colors.clone(), &dark, Some(&hues))?,
+        generate_palette_with_hues(colors, &light, Some(&hues))?,
+    ])
+}
+
+/// A fallback palette for images without usable colors: neutral base colors and
+/// highlights evenly spaced around the color wheel
+pub fn neutral_palette(settings: &PaletteSettings) -> Result<[Oklch<f64>; 24]> {
+    let colors = (0..8)
+        .map(|i| Oklch::new(0.5, 0.0, i as f64 * 45.0))
+        .collect();
+    generate_palette(
+        colors,
+        &PaletteSettings {
+            keep: 8,
+            background_tint: BackgroundTint::Neutral,
+            ..settings.clone()
+        },
+    )
+}
+
 pub fn generate_palette(
+    colors: Vec<Oklch<f64>>,
+    settings: &PaletteSettings,
+) -> Result<[Oklch<f64>; 24]> {
+    generate_palette_with_hues(colors, settings, None)
+}
+
+/// Generates a palette, optionally taking the highlight hues from another palette
+fn generate_palette_with_hues(
     mut colors: Vec<Oklch<f64>>,
     settings: &PaletteSettings,
+    hues: Option<&[Oklch<f64>; 24]>,
 ) -> Result<[Oklch<f64>; 24]> {
-    let base_hue = colors.first().expect("at least one color").hue;
-    let base_colors_it;
-    let base24_bg: [Oklch<f64>; 2];
-    match settings.style {
-        PaletteStyle::Dark => {
-            base_colors_it = Either::Right(1..=8);
-            base24_bg = [
-                Oklch::new(0.05, settings.base_chroma, base_hue),
-                Oklch::new(0.0, settings.base_chroma, base_hue),
-            ];
-        }
-        PaletteStyle::Light => {
-            base_colors_it = Either::Left((1..=8).rev());
-            base24_bg = [
-                Oklch::new(0.85, settings.base_chroma, base_hue),
-                Oklch::new(0.90, settings.base_chroma, base_hue),
-            ];
-        }
+    settings.validate()?;
+    // Auto styles and image tints depend on the image, so frontends have to resolve them
+    if settings.style == PaletteStyle::Auto || settings.background_tint == BackgroundTint::Image {
+        return Err(IroError::InvalidSettings(
+            "resolve the settings for the image before generating a palette".to_string(),
+        ));
+    }
+    if colors.is_empty() {
+        return Err(IroError::NoColorsFound);
+    }
+    let base_hue = match settings.background_tint {
+        BackgroundTint::Accent => colors[0].hue,
+        BackgroundTint::Hue(hue) => hue.into(),
+        BackgroundTint::Neutral | BackgroundTint::Image => 0.0.into(),
     };
-    let base_colors =
-        base_colors_it.map(|l| Oklch::new(l as f64 * 0.125, settings.base_chroma, base_hue));
-
-    let mut i = 0;
+    let base_chroma = match settings.background_tint {
+        BackgroundTint::Neutral => 0.0,
+        _ => settings.base_chroma,
+    };
+    let lightnesses = settings.ramp.lightnesses()?;
+    // base10 and base11 are darker backgrounds (or, for light styles, lighter-leaning tints)
+    // placed relative to base00
+    let bg_offsets = match settings.style.is_light() {
+        false => [-0.075, -0.125],
+        true => [-0.15, -0.10],
+    };
+    let base24_bg = bg_offsets.map(|offset| {
+        Oklch::new(
+            (lightnesses[0] + offset).clamp(0.0, 1.0),
+            base_chroma,
+            base_hue,
+        )
+    });
+    let base_colors = lightnesses
+        .into_iter()
+        .enumerate()
+        .map(|(i, l)| Oklch::new(l, settings.ramp.chroma(base_chroma, i), base_hue));
 
-    colors = colors.into_iter().take(settings.keep.min(8)).collect();
-    while colors.len() < 8 {
-        colors.push(colors[i]);
-        i += 1;
-    }
-    let (highlights, highlights_tee) = colors
+    colors = fill_hues(
+        colors.into_iter().take(settings.keep).collect(),
+        8,
+        settings.hue_fill,
+    );
+    let sources = colors
         .iter()
-        .sorted_unstable_by(|a, b| a.chroma.partial_cmp(&b.chroma).expect("comparable chromas"))
+        .sorted_unstable_by(|a, b| a.chroma.total_cmp(&b.chroma))
         .rev()
         .take(8)
-        .map(|color| color.hue)
-        .sorted_unstable_by_key(|hue| hue.into_positive_degrees() as u16)
-        .enumerate()
-        .sorted_unstable_by_key(|(i, _)| (i + settings.rotation) % 8)
-        .map(|(_, hue)| Oklch::new(settings.hl_lightness, settings.hl_chroma, hue))
-        .tee();
+        .sorted_unstable_by_key(|color| color.hue.into_positive_degrees() as u16)
+        .copied()
+        .collect_vec();
+    let sources = match settings.hue_assignment {
+        HueAssignment::Rotation => sources
+            .into_iter()
+            .enumerate()
+            .sorted_unstable_by_key(|(i, _)| (i + settings.rotation) % 8)
+            .map(|(_, color)| color)
+            .collect_vec(),
+        HueAssignment::Semantic => assign_semantic(&sources),
+    };
+    let background = Oklch::new(
+        lightnesses[0],
+        settings.ramp.chroma(base_chroma, 0),
+        base_hue,
+    );
+    let mut highlights = sources
+        .into_iter()
+        .map(|source| {
+            let l = settings.hl_lightness_mode.lightness(
+                &source,
+                settings.hl_lightness,
+                settings.hl_chroma,
+                settings.hl_lightness_spread,
+                &background,
+            );
+            // Blend towards the lightness and chroma of the extracted color
+            let blend = |normalized: f64, original: f64| {
+                normalized + (original - normalized) * settings.fidelity
+            };
+            Oklch::new(
+                blend(l, source.l),
+                blend(settings.hl_chroma, source.chroma),
+                source.hue,
+            )
+        })
+        .collect_vec();
+    separate_hues(
+        &mut highlights,
+        settings.min_hue_separation,
+        settings.min_delta_e,
+    );
 
-    let bright_highlights = highlights_tee.enumerate().filter_map(|(i, color)| {
+    let bright_highlights = highlights.iter().enumerate().filter_map(|(i, color)| {
         match i {
             // Base24 has 2 fewer bright highlight colors compared to highlight colors
             // We exclude base0a (index 2) since it doesn't correspond to a terminal color
             // and base0f (index 7) which is deprecated.
             // See: https://github.com/tinted-theming/base24/blob/18af13d81e31a37be3617891c0a9e7a87da0ade9/styling.md
             2 | 7 => None,
-            _ => Some(Oklch::new(color.l * 1.2, color.chroma * 1.2, color.hue)),
+            _ => Some(
+                settings
+                    .brights
+                    .derive(color, &background, settings.bright_chroma),
+            ),
         }
     });
 
-    color_array::<24>(
+    let mut palette = color_array::<24>(
         base_colors
-            .chain(highlights)
+            .chain(highlights.iter().copied())
             .chain(base24_bg)
             .chain(bright_highlights),
     )
-    .with_context(|| "Not enough colors")
+    .ok_or(IroError::NoColorsFound)?;
+
+    if let Some(min_delta_e) = settings.cvd_min_delta_e {
+        nudge_hues(&mut palette, min_delta_e);
+    }
+    if let Some(hues) = hues {
+        for slot in HIGHLIGHT_SLOTS {
+            palette[slot].hue = hues[slot].hue;
+        }
+    }
+
+    if let Some(min_contrast) = settings.min_contrast {
+        let background = palette[0x00];
+        for slot in FOREGROUND_SLOTS.into_iter().chain(HIGHLIGHT_SLOTS) {
+            palette[slot] = enforce_contrast(
+                &palette[slot],
+                &background,
+                settings.contrast_metric,
+                min_contrast,
+            );
+        }
+    }
+
+    for (&slot, &color) in &settings.overrides {
+        palette[slot] = color;
+    }
+
+    Ok(palette)
 }
@@ -0,0 +1,153 @@
+use clap::ValueEnum;
+use palette::Oklch;
+use serde::{Deserialize, Serialize};
+
+use std::ops::RangeInclusive;
+
+use crate::lch_to_rgb;
+
+/// How contrast between a foreground and a background color is measured
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ContrastMetric {
+    /// WCAG 2.x contrast ratio (1 to 21)
+    #[default]
+    Wcag,
+    /// APCA lightness contrast (absolute Lc, 0 to ~106)
+    Apca,
+}
+
+impl ContrastMetric {
+    /// The range of contrasts this metric can measure
+    pub fn range(self) -> RangeInclusive<f64> {
+        match self {
+            Self::Wcag => 1.0..=21.0,
+            Self::Apca => 0.0..=108.0,
+        }
+    }
+
+    pub fn contrast(self, foreground: &Oklch<f64>, background: &Oklch<f64>) -> f64 {
+        match self {
+            Self::Wcag => wcag_contrast(foreground, background),
+            Self::Apca => apca_contrast(foreground, background).abs(),
+        }
+    }
+}
+
+/// WCAG 2.x relative luminance of the color as it will be displayed in sRGB
+pub fn relative_luminance(color: &Oklch<f64>) -> f64 {
+    let [r, g, b] = lch_to_rgb(color).map(|c| {
+        let c = c as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+/// WCAG 2.x contrast ratio between two colors
+/// See: https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio
+pub fn wcag_contrast(a: &Oklch<f64>, b: &Oklch<f64>) -> f64 {
+    let (a, b) = (relative_luminance(a), relative_luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// APCA screen luminance, with the soft clamp for near-black colors applied
+fn apca_luminance(color: &Oklch<f64>) -> f64 {
+    const BLACK_THRESHOLD: f64 = 0.022;
+    const BLACK_CLAMP: f64 = 1.414;
+    let [r, g, b] = lch_to_rgb(color).map(|c| (c as f64 / 255.0).powf(2.4));
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    if y < BLACK_THRESHOLD {
+        y + (BLACK_THRESHOLD - y).powf(BLACK_CLAMP)
+    } else {
+        y
+    }
+}
+
+/// APCA lightness contrast (Lc) of text on a background.
+/// Positive for dark text on light backgrounds, negative for light text on dark backgrounds.
+/// See: https://github.com/Myndex/apca-w3 (0.0.98G-4g constants)
+pub fn apca_contrast(text: &Oklch<f64>, background: &Oklch<f64>) -> f64 {
+    const SCALE: f64 = 1.14;
+    const OFFSET: f64 = 0.027;
+    const LOW_CLIP: f64 = 0.1;
+    const DELTA_Y_MIN: f64 = 0.0005;
+
+    let text = apca_luminance(text);
+    let background = apca_luminance(background);
+    if (background - text).abs() < DELTA_Y_MIN {
+        return 0.0;
+    }
+
+    let contrast = if background > text {
+        let sapc = (background.powf(0.56) - text.powf(0.57)) * SCALE;
+        if sapc < LOW_CLIP {
+            0.0
+        } else {
+            sapc - OFFSET
+        }
+    } else {
+        let sapc = (background.powf(0.65) - text.powf(0.62)) * SCALE;
+        if sapc > -LOW_CLIP {
+            0.0
+        } else {
+            sapc + OFFSET
+        }
+    };
+    contrast * 100.0
+}
+
+/// Moves the lightness of `color` away from `background` until their contrast reaches
+/// `min_contrast` (or lightness runs out)
+pub fn enforce_contrast(
+    color: &Oklch<f64>,
+    background: &Oklch<f64>,
+    metric: ContrastMetric,
+    min_contrast: f64,
+) -> Oklch<f64> {
+    const STEP: f64 = 0.005;
+    let step = if background.l < 0.5 { STEP } else { -STEP };
+    let mut adjusted = *color;
+    while metric.contrast(&adjusted, background) < min_contrast
+        && (0.0..=1.0).contains(&(adjusted.l + step))
+    {
+        adjusted.l += step;
+    }
+    adjusted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex_to_lch;
+
+    fn color(hex: &str) -> Oklch<f64> {
+        hex_to_lch(hex).unwrap()
+    }
+
+    #[test]
+    fn wcag_reference_values() {
+        let (black, white) = (color("000000"), color("ffffff"));
+        assert!((wcag_contrast(&black, &white) - 21.0).abs() < 0.01);
+        assert!((wcag_contrast(&white, &black) - 21.0).abs() < 0.01);
+        assert!((wcag_contrast(&white, &white) - 1.0).abs() < 0.01);
+        // The lightest gray that still reaches AA on white is #767676 (4.54:1)
+        assert!((wcag_contrast(&color("767676"), &white) - 4.54).abs() < 0.01);
+        assert!(wcag_contrast(&color("777777"), &white) < 4.5);
+    }
+
+    #[test]
+    fn apca_reference_values() {
+        // Reference values from the APCA-W3 0.0.98G-4g test suite
+        let (black, white) = (color("000000"), color("ffffff"));
+        assert!((apca_contrast(&black, &white) - 106.04).abs() < 0.01);
+        assert!((apca_contrast(&white, &black) + 107.88).abs() < 0.01);
+        assert!((apca_contrast(&color("888888"), &white) - 63.06).abs() < 0.01);
+        assert!((apca_contrast(&white, &color("888888")) + 68.54).abs() < 0.01);
+        assert!((apca_contrast(&color("112233"), &color("ddeeff")) - 91.67).abs() < 0.01);
+        assert_eq!(apca_contrast(&white, &white), 0.0);
+    }
+}
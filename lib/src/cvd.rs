@@ -0,0 +1,148 @@
+use clap::ValueEnum;
+use itertools::Itertools;
+use palette::{IntoColor, LinSrgb, Oklch};
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Display;
+
+use crate::base24::BRIGHT_SOURCES;
+use crate::delta_e;
+use crate::gamut::gamut_map;
+
+/// Color-vision deficiencies that palettes can be simulated under
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Deficiency {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl Deficiency {
+    pub const ALL: [Self; 3] = [Self::Protanopia, Self::Deuteranopia, Self::Tritanopia];
+
+    /// Simulation matrices for linear RGB at full severity (Machado et al. 2009)
+    /// See: https://www.inf.ufrgs.br/~oliveira/pubs_files/CVD_Simulation/CVD_Simulation.html
+    fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            Self::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+}
+
+impl Display for Deficiency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Protanopia => "protanopia",
+                Self::Deuteranopia => "deuteranopia",
+                Self::Tritanopia => "tritanopia",
+            }
+        )
+    }
+}
+
+/// How a color (as displayed in sRGB) appears to someone with `deficiency`
+pub fn simulate(color: &Oklch<f64>, deficiency: Deficiency) -> Oklch<f64> {
+    let rgb: LinSrgb<f64> = gamut_map(color).into_color();
+    let rgb = [rgb.red, rgb.green, rgb.blue];
+    let [red, green, blue] = deficiency
+        .matrix()
+        .map(|row| (row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]).clamp(0.0, 1.0));
+    LinSrgb::new(red, green, blue).into_color()
+}
+
+pub fn simulate_palette<const N: usize>(
+    palette: &[Oklch<f64>; N],
+    deficiency: Deficiency,
+) -> [Oklch<f64>; N] {
+    palette.map(|color| simulate(&color, deficiency))
+}
+
+/// The slots of the eight Base24 highlight colors (base08 - base0F)
+const HIGHLIGHTS: std::ops::RangeInclusive<usize> = 0x08..=0x0F;
+
+/// The perceptual distance between two highlight slots under a simulation
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct PairDistance {
+    pub a: usize,
+    pub b: usize,
+    pub delta_e: f64,
+}
+
+/// Pairwise Oklab ΔE between the highlights of a Base24 palette under `deficiency`,
+/// closest pairs first
+pub fn highlight_distances(
+    palette: &[Oklch<f64>; 24],
+    deficiency: Deficiency,
+) -> Vec<PairDistance> {
+    let simulated = simulate_palette(palette, deficiency);
+    HIGHLIGHTS
+        .tuple_combinations()
+        .map(|(a, b)| PairDistance {
+            a,
+            b,
+            delta_e: delta_e(&simulated[a], &simulated[b]),
+        })
+        .sorted_unstable_by(|x, y| x.delta_e.total_cmp(&y.delta_e))
+        .collect()
+}
+
+/// The closest pair of highlights under any deficiency
+fn closest_pair(palette: &[Oklch<f64>; 24]) -> Option<PairDistance> {
+    Deficiency::ALL
+        .iter()
+        .filter_map(|deficiency| highlight_distances(palette, *deficiency).first().copied())
+        .min_by(|x, y| x.delta_e.total_cmp(&y.delta_e))
+}
+
+/// Pushes apart the hues (and, since hue differences are partly lost to the deficiencies,
+/// the lightness) of highlights that are hard to tell apart under any deficiency until every
+/// pair is at least `min_delta_e` apart (or the iteration budget runs out).
+/// Bright highlights follow the hues of the highlights they are derived from.
+pub fn nudge_hues(palette: &mut [Oklch<f64>; 24], min_delta_e: f64) {
+    const HUE_STEP: f64 = 1.0;
+    const LIGHTNESS_STEP: f64 = 0.002;
+    const MAX_ITERATIONS: usize = 360;
+
+    for _ in 0..MAX_ITERATIONS {
+        let Some(pair) = closest_pair(palette) else {
+            break;
+        };
+        if pair.delta_e >= min_delta_e {
+            break;
+        }
+        let difference = (palette[pair.b].hue - palette[pair.a].hue).into_degrees();
+        let direction = if difference >= 0.0 { 1.0 } else { -1.0 };
+        palette[pair.a].hue -= direction * HUE_STEP;
+        palette[pair.b].hue += direction * HUE_STEP;
+
+        let (darker, lighter) = if palette[pair.a].l <= palette[pair.b].l {
+            (pair.a, pair.b)
+        } else {
+            (pair.b, pair.a)
+        };
+        palette[darker].l = (palette[darker].l - LIGHTNESS_STEP).max(0.0);
+        palette[lighter].l = (palette[lighter].l + LIGHTNESS_STEP).min(1.0);
+    }
+
+    for (bright, source) in BRIGHT_SOURCES {
+        palette[bright].hue = palette[source].hue;
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+/// Everything that can go wrong while generating a scheme
+#[derive(Debug, Clone, PartialEq)]
+pub enum IroError {
+    /// The image had no colors to build a palette from
+    NoColorsFound,
+    /// A setting is out of range or could not be parsed
+    InvalidSettings(String),
+    /// A color could not be parsed
+    InvalidColor(String),
+    /// The image could not be decoded
+    ImageDecode(String),
+    /// A scheme could not be serialized
+    Serialize(String),
+}
+
+pub type Result<T, E = IroError> = std::result::Result<T, E>;
+
+impl Display for IroError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoColorsFound => write!(f, "no colors found in the image"),
+            Self::InvalidSettings(message) => write!(f, "invalid settings: {message}"),
+            Self::InvalidColor(message) => write!(f, "invalid color: {message}"),
+            Self::ImageDecode(message) => write!(f, "failed to decode image: {message}"),
+            Self::Serialize(message) => write!(f, "failed to serialize scheme: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for IroError {}
+
+/// Checks that the setting `name` is within `range`, returning it if so
+pub fn check_range<T>(name: &str, value: T, range: &RangeInclusive<T>) -> Result<T>
+where
+    T: PartialOrd + Display,
+{
+    match range.contains(&value) {
+        true => Ok(value),
+        false => Err(IroError::InvalidSettings(format!(
+            "{name} must be between {} and {}, got {value}",
+            range.start(),
+            range.end()
+        ))),
+    }
+}
+
+impl From<image::ImageError> for IroError {
+    fn from(error: image::ImageError) -> Self {
+        Self::ImageDecode(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for IroError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Serialize(error.to_string())
+    }
+}
+
+impl From<toml::ser::Error> for IroError {
+    fn from(error: toml::ser::Error) -> Self {
+        Self::Serialize(error.to_string())
+    }
+}
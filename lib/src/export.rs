@@ -0,0 +1,218 @@
+use crate::error::Result;
+use palette::{Clamp, IntoColor, Oklab, Oklch, Srgb};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+
+use std::fmt::Write;
+
+use crate::base24::{Base24Style, PaletteSettings};
+use crate::gamut::{gamut_map, oklch_css, Gamut};
+use crate::generator::{Base24Generator, PaletteGenerator};
+use crate::provenance::Provenance;
+use crate::{lch_to_hex, ParseColorsSettings};
+
+#[derive(Serialize, Debug, Clone)]
+pub struct RgbCoords {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OklabCoords {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct OklchCoords {
+    pub l: f64,
+    pub c: f64,
+    pub h: f64,
+}
+
+/// A palette slot mapped into a wide color gamut
+#[derive(Serialize, Debug, Clone)]
+pub struct WideGamutExport {
+    pub gamut: Gamut,
+    /// Hex color to be interpreted in `gamut` rather than sRGB
+    pub hex: String,
+    pub rgb: RgbCoords,
+    /// CSS `color()` function
+    pub css: String,
+    /// CSS `oklch()` function
+    pub css_oklch: String,
+}
+
+impl WideGamutExport {
+    pub fn new(color: &Oklch<f64>, gamut: Gamut) -> Self {
+        let [r, g, b] = gamut.rgb(color);
+        Self {
+            gamut,
+            hex: gamut.hex(color),
+            rgb: RgbCoords { r, g, b },
+            css: gamut.css(color),
+            css_oklch: oklch_css(color, gamut),
+        }
+    }
+}
+
+/// A single (gamut mapped) palette slot in every color space iro works with
+#[derive(Serialize, Debug, Clone)]
+pub struct ColorExport {
+    /// sRGB hex, which doubles as the fallback for wide gamut colors
+    pub hex: String,
+    pub srgb: RgbCoords,
+    pub oklab: OklabCoords,
+    pub oklch: OklchCoords,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wide_gamut: Option<WideGamutExport>,
+}
+
+impl ColorExport {
+    /// Exports a color, including its values in `gamut` unless that is sRGB
+    pub fn new(color: &Oklch<f64>, gamut: Gamut) -> Self {
+        let wide_gamut = (gamut != Gamut::Srgb).then(|| WideGamutExport::new(color, gamut));
+        let color = &gamut_map(color);
+        let srgb: Srgb<f64> = Srgb::from_linear((*color).into_color()).clamp();
+        let oklab: Oklab<f64> = (*color).into_color();
+        Self {
+            hex: lch_to_hex(color),
+            srgb: RgbCoords {
+                r: srgb.red,
+                g: srgb.green,
+                b: srgb.blue,
+            },
+            oklab: OklabCoords {
+                l: oklab.l,
+                a: oklab.a,
+                b: oklab.b,
+            },
+            oklch: OklchCoords {
+                l: color.l,
+                c: color.chroma,
+                h: color.hue.into_positive_degrees(),
+            },
+            wide_gamut,
+        }
+    }
+}
+
+/// Exports each color of a palette under the name of its slot in `G`'s layout, in slot order
+fn export_palette<G: PaletteGenerator>(
+    palette: &[Oklch<f64>],
+    gamut: Gamut,
+) -> Vec<(String, ColorExport)> {
+    palette
+        .iter()
+        .enumerate()
+        .map(|(slot, color)| (G::slot_name(slot), ColorExport::new(color, gamut)))
+        .collect()
+}
+
+/// A scheme along with the perceptual values and settings used to generate it
+#[derive(Serialize, Debug, Clone)]
+pub struct SchemeExport<'a> {
+    pub name: &'a str,
+    pub author: &'a str,
+    pub variant: &'a str,
+    /// The settings of Base24 palettes (custom generators keep their own settings)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette_settings: Option<&'a PaletteSettings>,
+    pub parse_colors_settings: &'a ParseColorsSettings,
+    /// Slots in slot order (rather than sorted by name, which puts color10 before color2)
+    #[serde(serialize_with = "serialize_slots")]
+    pub palette: Vec<(String, ColorExport)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<&'a Provenance>,
+}
+
+impl<'a> SchemeExport<'a> {
+    pub fn new(
+        style: &'a Base24Style,
+        palette_settings: &'a PaletteSettings,
+        parse_colors_settings: &'a ParseColorsSettings,
+        gamut: Gamut,
+    ) -> Self {
+        Self {
+            name: &style.name,
+            author: &style.author,
+            variant: &style.variant,
+            palette_settings: Some(palette_settings),
+            parse_colors_settings,
+            palette: export_palette::<Base24Generator>(&style.palette, gamut),
+            provenance: style.provenance.as_ref(),
+        }
+    }
+
+    /// Exports a palette generated by any [`PaletteGenerator`], e.g.
+    /// `SchemeExport::custom::<Ansi16Generator>(..)`
+    pub fn custom<G: PaletteGenerator>(
+        name: &'a str,
+        author: &'a str,
+        variant: &'a str,
+        palette: &G::Palette,
+        parse_colors_settings: &'a ParseColorsSettings,
+        gamut: Gamut,
+    ) -> Self {
+        Self {
+            name,
+            author,
+            variant,
+            palette_settings: None,
+            parse_colors_settings,
+            palette: export_palette::<G>(palette.as_ref(), gamut),
+            provenance: None,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// CSS custom properties (`--base00` etc.), overridden by wide gamut colors where supported
+    pub fn to_css(&self) -> String {
+        let mut css = String::from(":root {\n");
+        for (name, color) in &self.palette {
+            let _ = writeln!(css, "  --{name}: #{};", color.hex);
+        }
+        css.push_str("}\n");
+
+        let wide_colors = self
+            .palette
+            .iter()
+            .filter_map(|(name, color)| Some((name, color.wide_gamut.as_ref()?)))
+            .collect::<Vec<_>>();
+        if let Some((_, first)) = wide_colors.first() {
+            let _ = writeln!(
+                css,
+                "\n@supports (color: color({} 0 0 0)) {{",
+                first.gamut.css_name()
+            );
+            css.push_str("  :root {\n");
+            for (name, color) in wide_colors {
+                let _ = writeln!(css, "    --{name}: {};", color.css);
+            }
+            css.push_str("  }\n}\n");
+        }
+        css
+    }
+}
+
+fn serialize_slots<S>(slots: &[(String, ColorExport)], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut serializer = serializer.serialize_map(Some(slots.len()))?;
+
+    for (name, color) in slots {
+        serializer.serialize_entry(name, color)?;
+    }
+
+    serializer.end()
+}
@@ -0,0 +1,195 @@
+use clap::ValueEnum;
+use image::RgbImage;
+use itertools::Itertools;
+use palette::{cast::FromComponents, IntoColor, Oklab, Oklch, Srgb};
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Display;
+use std::ops::Div;
+
+use crate::error::Result;
+
+/// A color extracted from an image, with the fraction of the image's pixels it stands for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightedColor {
+    pub color: Oklch<f64>,
+    pub weight: f64,
+}
+
+/// Finds the prominent colors of an image
+pub trait ColorExtractor {
+    /// The extracted colors, most prominent first
+    fn extract(&self, image: &RgbImage) -> Result<Vec<WeightedColor>>;
+}
+
+/// The built-in color extractors
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExtractionMethod {
+    /// Average the vivid pixels in each segment of the color wheel
+    #[default]
+    HueSegments,
+    /// Cluster the vivid pixels in Oklab with k-means
+    KMeans,
+}
+
+impl Display for ExtractionMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::HueSegments => "hue-segments",
+                Self::KMeans => "k-means",
+            }
+        )
+    }
+}
+
+/// The pixels of an image in Oklch
+pub(crate) fn oklch_pixels(image: &RgbImage) -> Vec<Oklch<f64>> {
+    <&[Srgb<u8>]>::from_components(&**image)
+        .iter()
+        .map(|pixel| pixel.into_linear::<f64>().into_color())
+        .collect()
+}
+
+/// The average chroma of some pixels
+pub(crate) fn average_chroma(pixels: &[Oklch<f64>]) -> f64 {
+    pixels.iter().map(|pixel| pixel.chroma).sum::<f64>() / pixels.len() as f64
+}
+
+/// Pixels with at least the average chroma of the image
+fn vivid_pixels(image: &RgbImage) -> Vec<Oklch<f64>> {
+    let pixels = oklch_pixels(image);
+    let avg_chroma = average_chroma(&pixels);
+    pixels
+        .into_iter()
+        .filter(|pixel| pixel.chroma >= avg_chroma)
+        .collect()
+}
+
+/// Groups pixels by hue into `segment_size` segments of the color wheel and averages each
+/// segment
+#[derive(Debug, Clone, Copy)]
+pub struct HueSegments {
+    pub segment_size: f64,
+}
+
+impl ColorExtractor for HueSegments {
+    fn extract(&self, image: &RgbImage) -> Result<Vec<WeightedColor>> {
+        let total = (image.width() * image.height()).max(1) as f64;
+        Ok(vivid_pixels(image)
+            .iter()
+            .into_grouping_map_by(|pixel| {
+                // Map each pixel to a segment of the color wheel base on hue
+                pixel
+                    .hue
+                    .into_positive_degrees()
+                    .div(360.0 / self.segment_size)
+                    .floor() as u16
+            })
+            .fold((0, 0.0, 0.0, 0.0), |(count, l, c, h), _, pixel| {
+                (
+                    count + 1,
+                    l + pixel.l,
+                    c + pixel.chroma,
+                    h + pixel.hue.into_positive_degrees(),
+                )
+            })
+            .values()
+            .sorted_unstable_by_key(|(count, _, _, _)| count)
+            .rev()
+            .map(|(count, l, c, h)| {
+                let count = *count as f64;
+                WeightedColor {
+                    color: Oklch::new(l / count, c / count, h / count),
+                    weight: count / total,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Clusters pixels into (at most) `clusters` colors in Oklab
+#[derive(Debug, Clone, Copy)]
+pub struct KMeans {
+    pub clusters: usize,
+}
+
+impl ColorExtractor for KMeans {
+    fn extract(&self, image: &RgbImage) -> Result<Vec<WeightedColor>> {
+        const ITERATIONS: usize = 16;
+
+        let total = (image.width() * image.height()).max(1) as f64;
+        let pixels = vivid_pixels(image)
+            .into_iter()
+            .map(|pixel| -> Oklab<f64> { pixel.into_color() })
+            .collect_vec();
+        let distance = |a: &Oklab<f64>, b: &Oklab<f64>| {
+            (a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)
+        };
+        let nearest = |centroids: &[Oklab<f64>], pixel: &Oklab<f64>| {
+            centroids
+                .iter()
+                .map(|centroid| distance(centroid, pixel))
+                .position_min_by(f64::total_cmp)
+                .unwrap_or(0)
+        };
+
+        // Start from the most vivid pixel, then repeatedly add the pixel furthest from
+        // every centroid so far, which keeps the result deterministic
+        let mut centroids = pixels
+            .iter()
+            .max_by(|a, b| (a.a.hypot(a.b)).total_cmp(&b.a.hypot(b.b)))
+            .copied()
+            .into_iter()
+            .collect_vec();
+        while centroids.len() < self.clusters.min(pixels.len()) {
+            let furthest = pixels.iter().max_by(|a, b| {
+                let closest = |pixel| {
+                    centroids
+                        .iter()
+                        .map(|centroid| distance(centroid, pixel))
+                        .fold(f64::INFINITY, f64::min)
+                };
+                closest(a).total_cmp(&closest(b))
+            });
+            match furthest {
+                Some(pixel) if !centroids.contains(pixel) => centroids.push(*pixel),
+                _ => break,
+            }
+        }
+
+        let mut counts = vec![0; centroids.len()];
+        for _ in 0..ITERATIONS {
+            let mut sums = vec![(0.0, 0.0, 0.0); centroids.len()];
+            counts = vec![0; centroids.len()];
+            for pixel in &pixels {
+                let i = nearest(&centroids, pixel);
+                sums[i].0 += pixel.l;
+                sums[i].1 += pixel.a;
+                sums[i].2 += pixel.b;
+                counts[i] += 1;
+            }
+            for (centroid, ((l, a, b), count)) in centroids.iter_mut().zip(sums.iter().zip(&counts))
+            {
+                if *count > 0 {
+                    let count = *count as f64;
+                    *centroid = Oklab::new(l / count, a / count, b / count);
+                }
+            }
+        }
+
+        Ok(centroids
+            .into_iter()
+            .zip(counts)
+            .filter(|(_, count)| *count > 0)
+            .sorted_by(|a, b| b.1.cmp(&a.1))
+            .map(|(centroid, count)| WeightedColor {
+                color: centroid.into_color(),
+                weight: count as f64 / total,
+            })
+            .collect())
+    }
+}
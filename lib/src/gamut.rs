@@ -0,0 +1,289 @@
+use clap::ValueEnum;
+use palette::convert::IntoColorUnclamped;
+use palette::{LinSrgb, Oklch};
+use serde::{Deserialize, Serialize};
+
+use crate::delta_e;
+
+/// Just-noticeable difference in Oklab, below which clipping is preferred over reducing chroma
+const JND: f64 = 0.02;
+const EPSILON: f64 = 0.0001;
+
+type Matrix = [[f64; 3]; 3];
+
+// Conversion matrices between linear RGB spaces and CIE XYZ (D65)
+// See: https://www.w3.org/TR/css-color-4/#color-conversion-code
+#[rustfmt::skip]
+const SRGB_TO_XYZ: Matrix = [
+    [0.41239079926595934, 0.357584339383878, 0.1804807884018343],
+    [0.21263900587151027, 0.715168678767756, 0.07219231536073371],
+    [0.01933081871559182, 0.11919477979462598, 0.9505321522496607],
+];
+#[rustfmt::skip]
+const XYZ_TO_SRGB: Matrix = [
+    [3.2409699419045226, -1.537383177570094, -0.4986107602930034],
+    [-0.9692436362808796, 1.8759675015077202, 0.04155505740717559],
+    [0.05563007969699366, -0.20397695888897652, 1.0569715142428786],
+];
+#[rustfmt::skip]
+const P3_TO_XYZ: Matrix = [
+    [0.4865709486482162, 0.26566769316909306, 0.1982172852343625],
+    [0.2289745640697488, 0.6917385218365064, 0.079286914093745],
+    [0.0, 0.04511338185890264, 1.043944368900976],
+];
+#[rustfmt::skip]
+const XYZ_TO_P3: Matrix = [
+    [2.493496911941425, -0.9313836179191239, -0.40271078445071684],
+    [-0.8294889695615747, 1.7626640603183463, 0.023624685841943577],
+    [0.03584583024378447, -0.07617238926804182, 0.9568845240076872],
+];
+#[rustfmt::skip]
+const REC2020_TO_XYZ: Matrix = [
+    [0.6369580483012914, 0.14461690358620832, 0.1688809751641721],
+    [0.2627002120112671, 0.6779980715188708, 0.05930171646986196],
+    [0.0, 0.028072693049087428, 1.060985057710791],
+];
+#[rustfmt::skip]
+const XYZ_TO_REC2020: Matrix = [
+    [1.7166511879712674, -0.35567078377639233, -0.25336628137365974],
+    [-0.6666843518324892, 1.6164812366349395, 0.01576854581391113],
+    [0.017639857445310783, -0.042770613257808524, 0.9421031212354738],
+];
+
+fn multiply(m: &Matrix, [x, y, z]: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * x + row[1] * y + row[2] * z)
+}
+
+/// The RGB color spaces that palettes can be gamut mapped into
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Gamut {
+    #[default]
+    Srgb,
+    DisplayP3,
+    Rec2020,
+}
+
+impl Gamut {
+    /// The name of the color space in CSS `color()` functions
+    pub fn css_name(self) -> &'static str {
+        match self {
+            Self::Srgb => "srgb",
+            Self::DisplayP3 => "display-p3",
+            Self::Rec2020 => "rec2020",
+        }
+    }
+
+    fn to_linear(self, color: &Oklch<f64>) -> [f64; 3] {
+        let rgb: LinSrgb<f64> = (*color).into_color_unclamped();
+        let rgb = [rgb.red, rgb.green, rgb.blue];
+        match self {
+            Self::Srgb => rgb,
+            Self::DisplayP3 => multiply(&XYZ_TO_P3, multiply(&SRGB_TO_XYZ, rgb)),
+            Self::Rec2020 => multiply(&XYZ_TO_REC2020, multiply(&SRGB_TO_XYZ, rgb)),
+        }
+    }
+
+    fn oklch_from_linear(self, rgb: [f64; 3]) -> Oklch<f64> {
+        let [red, green, blue] = match self {
+            Self::Srgb => rgb,
+            Self::DisplayP3 => multiply(&XYZ_TO_SRGB, multiply(&P3_TO_XYZ, rgb)),
+            Self::Rec2020 => multiply(&XYZ_TO_SRGB, multiply(&REC2020_TO_XYZ, rgb)),
+        };
+        LinSrgb::new(red, green, blue).into_color_unclamped()
+    }
+
+    /// Applies the transfer function of the color space to a linear component
+    fn encode(self, c: f64) -> f64 {
+        match self {
+            Self::Srgb | Self::DisplayP3 => {
+                if c <= 0.0031308 {
+                    12.92 * c
+                } else {
+                    1.055 * c.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            Self::Rec2020 => {
+                const ALPHA: f64 = 1.09929682680944;
+                const BETA: f64 = 0.018053968510807;
+                if c < BETA {
+                    4.5 * c
+                } else {
+                    ALPHA * c.powf(0.45) - (ALPHA - 1.0)
+                }
+            }
+        }
+    }
+
+    pub fn contains(self, color: &Oklch<f64>) -> bool {
+        self.to_linear(color)
+            .iter()
+            .all(|c| (-EPSILON..=1.0 + EPSILON).contains(c))
+    }
+
+    fn clip(self, color: &Oklch<f64>) -> Oklch<f64> {
+        self.oklch_from_linear(self.to_linear(color).map(|c| c.clamp(0.0, 1.0)))
+    }
+
+    /// The gamma-encoded RGB components (0-1) of the color after mapping it into this gamut
+    pub fn rgb(self, color: &Oklch<f64>) -> [f64; 3] {
+        self.to_linear(&gamut_map_to(color, self))
+            .map(|c| self.encode(c.clamp(0.0, 1.0)))
+    }
+
+    /// The color as hex, interpreted in this color space
+    pub fn hex(self, color: &Oklch<f64>) -> String {
+        let [r, g, b] = self.rgb(color).map(|c| (c * 255.0).round() as u8);
+        format!("{r:02x}{g:02x}{b:02x}")
+    }
+
+    /// The color as a CSS `color()` function in this color space
+    pub fn css(self, color: &Oklch<f64>) -> String {
+        let [r, g, b] = self.rgb(color);
+        format!("color({} {r:.4} {g:.4} {b:.4})", self.css_name())
+    }
+}
+
+/// The color as a CSS `oklch()` function, after mapping it into `gamut`
+pub fn oklch_css(color: &Oklch<f64>, gamut: Gamut) -> String {
+    let color = gamut_map_to(color, gamut);
+    format!(
+        "oklch({:.4} {:.4} {:.2})",
+        color.l,
+        color.chroma,
+        color.hue.into_positive_degrees()
+    )
+}
+
+/// Maps a color into the sRGB gamut by reducing its chroma at constant lightness and hue
+pub fn gamut_map(color: &Oklch<f64>) -> Oklch<f64> {
+    gamut_map_to(color, Gamut::Srgb)
+}
+
+/// Maps a color into `gamut` by reducing its chroma at constant lightness and hue
+/// See: https://www.w3.org/TR/css-color-4/#binsearch
+pub fn gamut_map_to(color: &Oklch<f64>, gamut: Gamut) -> Oklch<f64> {
+    if color.l >= 1.0 {
+        return Oklch::new(1.0, 0.0, color.hue);
+    }
+    if color.l <= 0.0 {
+        return Oklch::new(0.0, 0.0, color.hue);
+    }
+    if gamut.contains(color) {
+        return *color;
+    }
+
+    let mut current = *color;
+    let mut clipped = gamut.clip(&current);
+    if delta_e(&clipped, &current) < JND {
+        return clipped;
+    }
+
+    let mut min = 0.0;
+    let mut max = color.chroma;
+    let mut min_in_gamut = true;
+    while max - min > EPSILON {
+        current.chroma = (min + max) / 2.0;
+        if min_in_gamut && gamut.contains(&current) {
+            min = current.chroma;
+            continue;
+        }
+        clipped = gamut.clip(&current);
+        let error = delta_e(&clipped, &current);
+        if error < JND {
+            if JND - error < EPSILON {
+                break;
+            }
+            min_in_gamut = false;
+            min = current.chroma;
+        } else {
+            max = current.chroma;
+        }
+    }
+    clipped
+}
+
+/// A palette slot that had to be gamut mapped
+#[derive(Debug, Clone, Copy)]
+pub struct GamutAdjustment {
+    pub slot: usize,
+    pub original: Oklch<f64>,
+    pub mapped: Oklch<f64>,
+}
+
+impl GamutAdjustment {
+    /// How much chroma was removed to bring the color into gamut
+    pub fn chroma_reduction(&self) -> f64 {
+        self.original.chroma - self.mapped.chroma
+    }
+
+    /// The perceptual distance between the requested and the mapped color
+    pub fn delta_e(&self) -> f64 {
+        delta_e(&self.original, &self.mapped)
+    }
+}
+
+/// Lists the palette slots that fall outside of `gamut`
+pub fn gamut_report(palette: &[Oklch<f64>], gamut: Gamut) -> Vec<GamutAdjustment> {
+    palette
+        .iter()
+        .enumerate()
+        .filter(|(_, color)| !gamut.contains(color))
+        .map(|(slot, color)| GamutAdjustment {
+            slot,
+            original: *color,
+            mapped: gamut_map_to(color, gamut),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hex_to_lch, lch_to_hex};
+
+    #[test]
+    fn in_gamut_colors_are_unchanged() {
+        let red = hex_to_lch("ff0000").unwrap();
+        assert_eq!(gamut_map_to(&red, Gamut::Srgb), red);
+        let gray = Oklch::new(0.5, 0.0, 0.0);
+        assert_eq!(gamut_map_to(&gray, Gamut::Rec2020), gray);
+    }
+
+    #[test]
+    fn lightness_extremes_map_to_black_and_white() {
+        let mapped = gamut_map_to(&Oklch::new(1.2, 0.3, 90.0), Gamut::Srgb);
+        assert_eq!((mapped.l, mapped.chroma), (1.0, 0.0));
+        let mapped = gamut_map_to(&Oklch::new(-0.1, 0.3, 90.0), Gamut::Srgb);
+        assert_eq!((mapped.l, mapped.chroma), (0.0, 0.0));
+    }
+
+    #[test]
+    fn out_of_gamut_colors_lose_chroma_at_constant_lightness_and_hue() {
+        // sRGB red (oklch(0.628 0.258 29.23)) with more chroma maps back onto red
+        let red = hex_to_lch("ff0000").unwrap();
+        let vivid = Oklch::new(red.l, 0.4, red.hue);
+        let mapped = gamut_map_to(&vivid, Gamut::Srgb);
+        assert!(Gamut::Srgb.contains(&mapped));
+        assert_eq!(lch_to_hex(&mapped), "ff0000");
+
+        let green = Oklch::new(0.8, 0.45, 145.0);
+        assert!(!Gamut::Rec2020.contains(&green));
+        for gamut in [Gamut::Srgb, Gamut::DisplayP3, Gamut::Rec2020] {
+            let mapped = gamut_map_to(&green, gamut);
+            assert!(gamut.contains(&mapped));
+            assert!(mapped.chroma < green.chroma);
+            assert!((mapped.l - green.l).abs() < 0.01);
+            // The final clip moves the color by less than a just-noticeable difference
+            let reduced = Oklch {
+                chroma: mapped.chroma,
+                ..green
+            };
+            assert!(delta_e(&mapped, &reduced) < JND);
+        }
+        // Wider gamuts keep more of the chroma
+        let chroma = |gamut| gamut_map_to(&green, gamut).chroma;
+        assert!(chroma(Gamut::Srgb) < chroma(Gamut::DisplayP3));
+        assert!(chroma(Gamut::DisplayP3) < chroma(Gamut::Rec2020));
+    }
+}
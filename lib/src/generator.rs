@@ -0,0 +1,77 @@
+use image::RgbImage;
+use palette::Oklch;
+
+use crate::base24::{generate_palette, PaletteSettings, ANSI_SLOTS};
+use crate::error::Result;
+use crate::extractor::ColorExtractor;
+
+/// Turns the colors extracted from an image into a palette with a specific layout
+pub trait PaletteGenerator {
+    /// The generated colors, in slot order
+    type Palette: AsRef<[Oklch<f64>]>;
+
+    /// The name of a slot, as used by exporters (e.g. `base08` or `color1`)
+    fn slot_name(slot: usize) -> String;
+
+    /// Generates a palette from extracted colors, most prominent first
+    fn generate(&self, colors: Vec<Oklch<f64>>) -> Result<Self::Palette>;
+
+    /// Extracts the colors of an image with `extractor` and generates a palette from them
+    fn generate_from_image(
+        &self,
+        image: &RgbImage,
+        extractor: &dyn ColorExtractor,
+    ) -> Result<Self::Palette> {
+        let colors = extractor.extract(image)?;
+        self.generate(colors.into_iter().map(|weighted| weighted.color).collect())
+    }
+}
+
+/// The Base24 layout (base00 - base17)
+#[derive(Debug, Clone, Default)]
+pub struct Base24Generator {
+    pub settings: PaletteSettings,
+}
+
+impl Base24Generator {
+    pub fn new(settings: PaletteSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl PaletteGenerator for Base24Generator {
+    type Palette = [Oklch<f64>; 24];
+
+    fn slot_name(slot: usize) -> String {
+        format!("base{slot:02X}")
+    }
+
+    fn generate(&self, colors: Vec<Oklch<f64>>) -> Result<Self::Palette> {
+        generate_palette(colors, &self.settings)
+    }
+}
+
+/// The 16 ANSI terminal colors (color0 - color15), taken from a Base24 palette
+#[derive(Debug, Clone, Default)]
+pub struct Ansi16Generator {
+    pub settings: PaletteSettings,
+}
+
+impl Ansi16Generator {
+    pub fn new(settings: PaletteSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl PaletteGenerator for Ansi16Generator {
+    type Palette = [Oklch<f64>; 16];
+
+    fn slot_name(slot: usize) -> String {
+        format!("color{slot}")
+    }
+
+    fn generate(&self, colors: Vec<Oklch<f64>>) -> Result<Self::Palette> {
+        let palette = generate_palette(colors, &self.settings)?;
+        Ok(ANSI_SLOTS.map(|slot| palette[slot]))
+    }
+}
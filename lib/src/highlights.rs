@@ -0,0 +1,210 @@
+use clap::ValueEnum;
+use palette::Oklch;
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::base24::{PaletteSettings, PaletteStyle};
+use crate::contrast::{apca_contrast, ContrastMetric};
+use crate::error::{check_range, IroError, Result};
+
+/// How the lightness of each highlight color is chosen
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightLightness {
+    /// Every highlight uses `hl_lightness`
+    #[default]
+    Fixed,
+    /// Each highlight keeps the lightness of its source color, within `hl_lightness_spread`
+    /// of `hl_lightness`
+    Source,
+    /// Each highlight gets the lightness that gives it the same APCA contrast against the
+    /// background as a gray at `hl_lightness`
+    Apca,
+}
+
+impl Display for HighlightLightness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Fixed => "fixed",
+                Self::Source => "source",
+                Self::Apca => "apca",
+            }
+        )
+    }
+}
+
+impl HighlightLightness {
+    pub fn lightness(
+        self,
+        source: &Oklch<f64>,
+        hl_lightness: f64,
+        hl_chroma: f64,
+        spread: f64,
+        background: &Oklch<f64>,
+    ) -> f64 {
+        match self {
+            Self::Fixed => hl_lightness,
+            Self::Source => source
+                .l
+                .clamp(hl_lightness - spread, hl_lightness + spread)
+                .clamp(0.0, 1.0),
+            Self::Apca => {
+                let target = apca_contrast(&Oklch::new(hl_lightness, 0.0, 0.0), background).abs();
+                equalize_apca(
+                    &Oklch::new(hl_lightness, hl_chroma, source.hue),
+                    background,
+                    target,
+                )
+            }
+        }
+    }
+}
+
+/// How the bright highlights (base12 - base17) are derived from the highlights
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BrightDerivation {
+    /// Multiply lightness and chroma
+    Scale { lightness: f64, chroma: f64 },
+    /// Move lightness this far away from the background
+    Offset { lightness: f64 },
+    /// Add this much APCA contrast (Lc) against the background
+    Apca { delta: f64 },
+}
+
+impl BrightDerivation {
+    /// The valid range of scale factors
+    pub const SCALE: RangeInclusive<f64> = 0.0..=4.0;
+
+    pub fn default_for(style: PaletteStyle) -> Self {
+        match style.is_light() {
+            false => Self::Scale {
+                lightness: 1.2,
+                chroma: 1.2,
+            },
+            true => Self::Offset { lightness: 0.08 },
+        }
+    }
+
+    /// Checks that the amount is within its valid range
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Self::Scale { lightness, chroma } => {
+                check_range("bright lightness factor", lightness, &Self::SCALE)?;
+                check_range("bright chroma factor", chroma, &Self::SCALE)?;
+            }
+            Self::Offset { lightness } => {
+                check_range(
+                    "bright lightness offset",
+                    lightness,
+                    &PaletteSettings::LIGHTNESS,
+                )?;
+            }
+            Self::Apca { delta } => {
+                check_range("bright contrast", delta, &ContrastMetric::Apca.range())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The bright variant of `color`, optionally with a fixed `chroma`
+    pub fn derive(
+        self,
+        color: &Oklch<f64>,
+        background: &Oklch<f64>,
+        chroma: Option<f64>,
+    ) -> Oklch<f64> {
+        let direction = if color.l >= background.l { 1.0 } else { -1.0 };
+        let (l, derived_chroma) = match self {
+            Self::Scale {
+                lightness,
+                chroma: chroma_scale,
+            } => (color.l * lightness, color.chroma * chroma_scale),
+            Self::Offset { lightness } => (color.l + direction * lightness, color.chroma),
+            Self::Apca { delta } => {
+                let target = apca_contrast(color, background).abs() + delta;
+                (equalize_apca(color, background, target), color.chroma)
+            }
+        };
+        Oklch::new(
+            l.clamp(0.0, 1.0),
+            chroma.unwrap_or(derived_chroma),
+            color.hue,
+        )
+    }
+}
+
+impl Default for BrightDerivation {
+    fn default() -> Self {
+        Self::default_for(PaletteStyle::Dark)
+    }
+}
+
+impl Display for BrightDerivation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Scale { lightness, chroma } => write!(f, "scale:{lightness},{chroma}"),
+            Self::Offset { lightness } => write!(f, "offset:{lightness}"),
+            Self::Apca { delta } => write!(f, "apca:{delta}"),
+        }
+    }
+}
+
+impl FromStr for BrightDerivation {
+    type Err = IroError;
+
+    /// Parses `scale:<factor>[,<chroma factor>]`, `offset:<lightness>` or `apca:<Lc>`
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            IroError::InvalidSettings(format!(
+                "expected scale:<FACTOR>[,<CHROMA FACTOR>], offset:<L> or apca:<LC>, got {s:?}"
+            ))
+        };
+        let (mode, amount) = s.split_once(':').ok_or_else(invalid)?;
+        let parse = |amount: &str| amount.trim().parse::<f64>().map_err(|_| invalid());
+        Ok(match mode {
+            "scale" => {
+                let (lightness, chroma) = match amount.split_once(',') {
+                    Some((lightness, chroma)) => (parse(lightness)?, parse(chroma)?),
+                    None => (parse(amount)?, parse(amount)?),
+                };
+                Self::Scale { lightness, chroma }
+            }
+            "offset" => Self::Offset {
+                lightness: parse(amount)?,
+            },
+            "apca" => Self::Apca {
+                delta: parse(amount)?,
+            },
+            _ => return Err(invalid()),
+        })
+    }
+}
+
+/// Finds the lightness at which `color` has an APCA contrast of `target` against
+/// `background`, searching on the side of the background that `color` is on
+fn equalize_apca(color: &Oklch<f64>, background: &Oklch<f64>, target: f64) -> f64 {
+    const ITERATIONS: usize = 24;
+    let lighter = color.l >= background.l;
+    let (mut min, mut max) = match lighter {
+        true => (background.l, 1.0),
+        false => (0.0, background.l),
+    };
+    for _ in 0..ITERATIONS {
+        let l = (min + max) / 2.0;
+        let contrast = apca_contrast(&Oklch::new(l, color.chroma, color.hue), background).abs();
+        // Contrast grows as lightness moves away from the background
+        if (contrast < target) == lighter {
+            min = l;
+        } else {
+            max = l;
+        }
+    }
+    (min + max) / 2.0
+}
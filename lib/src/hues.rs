@@ -0,0 +1,191 @@
+use clap::ValueEnum;
+use itertools::Itertools;
+use palette::{OklabHue, Oklch};
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Display;
+
+use crate::delta_e;
+
+/// How extracted hues are assigned to the highlight slots (base08 - base0F)
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HueAssignment {
+    /// Sort hues by angle and shift them by `rotation`
+    #[default]
+    Rotation,
+    /// Give each slot the hue closest to its conventional color (base08 red, base0B green, ...)
+    Semantic,
+}
+
+/// How missing highlight hues are filled in when fewer than eight colors are kept
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HueFill {
+    /// Repeat the kept colors
+    #[default]
+    Repeat,
+    /// The complement of the dominant hue, then split complements
+    Complementary,
+    /// Neighbors of the dominant hue, further and further away
+    Analogous,
+    /// The triad of the dominant hue, then the hues in between
+    Triadic,
+    /// Hues evenly spaced around the wheel from the dominant hue
+    Even,
+}
+
+impl HueFill {
+    /// Offsets (in degrees) from the dominant hue to try, in order
+    fn offsets(self) -> &'static [f64] {
+        match self {
+            Self::Repeat => &[],
+            Self::Complementary => &[180.0, 150.0, 210.0, 30.0, 330.0, 120.0, 240.0],
+            Self::Analogous => &[30.0, 330.0, 60.0, 300.0, 90.0, 270.0, 120.0, 240.0],
+            Self::Triadic => &[120.0, 240.0, 60.0, 180.0, 300.0],
+            Self::Even => &[45.0, 90.0, 135.0, 180.0, 225.0, 270.0, 315.0],
+        }
+    }
+}
+
+impl Display for HueFill {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Repeat => "repeat",
+                Self::Complementary => "complementary",
+                Self::Analogous => "analogous",
+                Self::Triadic => "triadic",
+                Self::Even => "even",
+            }
+        )
+    }
+}
+
+/// Pads `colors` to `count` colors. Harmonic fills take the lightness and chroma of the
+/// dominant (first) color and skip hues that are too close to ones already present,
+/// splitting the widest gap between hues once the harmony runs out.
+pub fn fill_hues(mut colors: Vec<Oklch<f64>>, count: usize, fill: HueFill) -> Vec<Oklch<f64>> {
+    const MIN_SEPARATION: f64 = 15.0;
+
+    let dominant = colors[0];
+    let mut offsets = fill.offsets().iter();
+    let mut i = 0;
+    while colors.len() < count {
+        if fill == HueFill::Repeat {
+            colors.push(colors[i]);
+            i += 1;
+            continue;
+        }
+        let is_distinct = |hue: OklabHue<f64>| {
+            colors
+                .iter()
+                .all(|color| hue_distance(color.hue, hue) >= MIN_SEPARATION)
+        };
+        let hue = offsets
+            .by_ref()
+            .map(|offset| dominant.hue + *offset)
+            .find(|hue| is_distinct(*hue))
+            .unwrap_or_else(|| widest_gap_midpoint(&colors));
+        colors.push(Oklch::new(dominant.l, dominant.chroma, hue));
+    }
+    colors
+}
+
+/// The hue halfway across the widest gap between the hues of `colors`
+fn widest_gap_midpoint(colors: &[Oklch<f64>]) -> OklabHue<f64> {
+    let hues = colors
+        .iter()
+        .map(|color| color.hue.into_positive_degrees())
+        .sorted_unstable_by(f64::total_cmp)
+        .collect_vec();
+    let (start, gap) = hues
+        .iter()
+        .zip(hues.iter().cycle().skip(1))
+        .map(|(&a, &b)| (a, (b - a).rem_euclid(360.0)))
+        .map(|(a, gap)| (a, if gap == 0.0 { 360.0 } else { gap }))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .expect("at least one color");
+    (start + gap / 2.0).into()
+}
+
+/// Spreads out clustered highlights until neighboring hues are at least `min_hue` degrees
+/// and `min_delta_e` apart (or the iteration budget runs out), without changing the order
+/// of the hues around the color wheel
+pub fn separate_hues(colors: &mut [Oklch<f64>], min_hue: Option<f64>, min_delta_e: Option<f64>) {
+    const STEP: f64 = 0.5;
+    const MAX_ITERATIONS: usize = 720;
+
+    if colors.len() < 2 || (min_hue.is_none() && min_delta_e.is_none()) {
+        return;
+    }
+    // More separation than fits around the wheel can never be satisfied
+    let min_hue = min_hue.map(|min_hue| min_hue.min(360.0 / colors.len() as f64));
+    let too_close = |a: &Oklch<f64>, b: &Oklch<f64>| {
+        min_hue.is_some_and(|min_hue| hue_distance(a.hue, b.hue) < min_hue)
+            || min_delta_e.is_some_and(|min_delta_e| delta_e(a, b) < min_delta_e)
+    };
+
+    for _ in 0..MAX_ITERATIONS {
+        let order = (0..colors.len())
+            .sorted_unstable_by(|&a, &b| {
+                colors[a]
+                    .hue
+                    .into_positive_degrees()
+                    .total_cmp(&colors[b].hue.into_positive_degrees())
+            })
+            .collect_vec();
+        let neighbors = order.iter().copied().circular_tuple_windows::<(_, _)>();
+        let mut moved = false;
+        for (a, b) in neighbors {
+            if a == b || !too_close(&colors[a], &colors[b]) {
+                continue;
+            }
+            // Only move a hue if it has room before reaching its other neighbor
+            let position = |i| order.iter().position(|&j| j == i).expect("in order");
+            let before = order[(position(a) + order.len() - 1) % order.len()];
+            let after = order[(position(b) + 1) % order.len()];
+            if (colors[a].hue - colors[before].hue).into_positive_degrees() > 2.0 * STEP {
+                colors[a].hue -= STEP;
+                moved = true;
+            }
+            if (colors[after].hue - colors[b].hue).into_positive_degrees() > 2.0 * STEP {
+                colors[b].hue += STEP;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+}
+
+/// The conventional hues (in Oklch degrees) of the highlight slots:
+/// red, orange, yellow, green, cyan, blue, magenta and brown
+/// See: https://github.com/tinted-theming/base24/blob/18af13d81e31a37be3617891c0a9e7a87da0ade9/styling.md
+pub const CANONICAL_HUES: [f64; 8] = [25.0, 60.0, 100.0, 145.0, 195.0, 255.0, 325.0, 45.0];
+
+/// Angular distance between two hues, in degrees
+pub fn hue_distance(a: OklabHue<f64>, b: OklabHue<f64>) -> f64 {
+    (a - b).into_degrees().abs()
+}
+
+/// Orders `colors` so that each lands in the slot whose canonical hue it is closest to,
+/// minimizing the total squared hue distance over all slots
+pub fn assign_semantic(colors: &[Oklch<f64>]) -> Vec<Oklch<f64>> {
+    let cost = |order: &[usize]| -> f64 {
+        order
+            .iter()
+            .zip(CANONICAL_HUES)
+            .map(|(&i, canonical)| hue_distance(colors[i].hue, canonical.into()).powi(2))
+            .sum()
+    };
+    // There are at most 8! orderings, so an exhaustive search is cheap enough
+    (0..colors.len())
+        .permutations(colors.len().min(CANONICAL_HUES.len()))
+        .min_by(|a, b| cost(a).total_cmp(&cost(b)))
+        .map(|order| order.into_iter().map(|i| colors[i]).collect())
+        .unwrap_or_default()
+}
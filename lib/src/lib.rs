@@ -1,63 +1,172 @@
+pub mod audit;
 pub mod base24;
+pub mod contrast;
+pub mod cvd;
+pub mod error;
+pub mod export;
+pub mod extractor;
+pub mod gamut;
+pub mod generator;
+pub mod highlights;
+pub mod hues;
+pub mod provenance;
+pub mod ramp;
+pub mod settings;
 pub use base24::{generate_palette, Base24Style};
+pub use error::{check_range, IroError, Result};
 
+use extractor::{
+    average_chroma, oklch_pixels, ColorExtractor, ExtractionMethod, HueSegments, KMeans,
+};
 use image::RgbImage;
 use itertools::Itertools;
 pub use palette::Oklch;
-use palette::{cast::FromComponents, IntoColor, Srgb};
-use std::ops::Div;
+use palette::{IntoColor, Oklab, Srgb};
+use serde::{Deserialize, Serialize};
+use std::ops::{Div, RangeInclusive};
 
+/// The version of iro used to generate schemes
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ParseColorsSettings {
+    /// How colors are extracted from the image
+    #[serde(default)]
+    pub method: ExtractionMethod,
     pub segment_size: f64,
+    /// The number of clusters for k-means extraction
+    #[serde(default = "default_clusters")]
+    pub clusters: usize,
+}
+
+fn default_clusters() -> usize {
+    8
+}
+
+impl ParseColorsSettings {
+    /// The valid number of color wheel segments
+    pub const SEGMENT_SIZE: RangeInclusive<f64> = 1.0..=360.0;
+    /// The valid number of k-means clusters
+    pub const CLUSTERS: RangeInclusive<usize> = 1..=16;
+
+    pub fn new(method: ExtractionMethod, segment_size: f64, clusters: usize) -> Result<Self> {
+        let settings = Self {
+            method,
+            segment_size,
+            clusters,
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Checks that every setting is within its valid range
+    pub fn validate(&self) -> Result<()> {
+        check_range("segment_size", self.segment_size, &Self::SEGMENT_SIZE)?;
+        check_range("clusters", self.clusters, &Self::CLUSTERS)?;
+        Ok(())
+    }
+
+    /// The extractor for the chosen method
+    pub fn extractor(&self) -> Box<dyn ColorExtractor> {
+        match self.method {
+            ExtractionMethod::HueSegments => Box::new(HueSegments {
+                segment_size: self.segment_size,
+            }),
+            ExtractionMethod::KMeans => Box::new(KMeans {
+                clusters: self.clusters,
+            }),
+        }
+    }
 }
 
 impl Default for ParseColorsSettings {
     fn default() -> Self {
-        Self { segment_size: 15.0 }
+        Self {
+            method: ExtractionMethod::HueSegments,
+            segment_size: 15.0,
+            clusters: default_clusters(),
+        }
     }
 }
 
+/// Euclidean distance between two colors in Oklab
+pub fn delta_e(a: &Oklch<f64>, b: &Oklch<f64>) -> f64 {
+    let a: Oklab<f64> = (*a).into_color();
+    let b: Oklab<f64> = (*b).into_color();
+    ((a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)).sqrt()
+}
+
+/// Converts a color to sRGB, gamut mapping it first if needed
+pub fn lch_to_rgb(color: &Oklch<f64>) -> [u8; 3] {
+    let rgb: Srgb<u8> = Srgb::from_linear(gamut::gamut_map(color).into_color());
+    [rgb.red, rgb.green, rgb.blue]
+}
+
 pub fn lch_to_hex(color: &Oklch<f64>) -> String {
-    let rgb: Srgb<u8> = Srgb::from_linear((*color).into_color());
-    format!("{0:02x}{1:02x}{2:02x}", rgb.red, rgb.green, rgb.blue)
+    let [r, g, b] = lch_to_rgb(color);
+    format!("{r:02x}{g:02x}{b:02x}")
 }
 
-pub fn parse_colors(image: &mut RgbImage, settings: &ParseColorsSettings) -> Vec<Oklch<f64>> {
-    // Put image into OkLab color space
-    let oklab_image = <&[Srgb<u8>]>::from_components(&**image)
-        .iter()
-        .map(|pixel| pixel.into_linear::<f64>().into_color())
-        .collect::<Vec<Oklch<f64>>>();
+/// Parses a hex color such as `ff5555` or `#ff5555`
+pub fn hex_to_lch(hex: &str) -> Result<Oklch<f64>> {
+    let rgb: Srgb<u8> = hex
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|error| IroError::InvalidColor(format!("{hex:?} ({error})")))?;
+    Ok(rgb.into_linear::<f64>().into_color())
+}
+
+/// Decodes an image file (in any format supported by the `image` crate)
+pub fn decode_image(image_bytes: &[u8]) -> Result<RgbImage> {
+    Ok(image::load_from_memory(image_bytes)?.into_rgb8())
+}
 
-    // Drop pixels with low chroma
-    let avg_chroma =
-        oklab_image.iter().map(|pixel| pixel.chroma).sum::<f64>() / oklab_image.len() as f64;
+/// The average Oklch lightness of an image
+pub fn mean_lightness(image: &RgbImage) -> f64 {
+    let pixels = oklch_pixels(image);
+    let total = pixels.iter().map(|pixel| pixel.l).sum::<f64>();
+    total / pixels.len().max(1) as f64
+}
+
+/// The hue (in degrees) of the most common low-chroma pixels in an image, which are
+/// usually what a wallpaper's background tone is made of
+pub fn background_hue(image: &RgbImage, settings: &ParseColorsSettings) -> Option<f64> {
+    // Below this chroma, hues are mostly noise
+    const MIN_CHROMA: f64 = 0.005;
+
+    let pixels = oklch_pixels(image);
+    let avg_chroma = average_chroma(&pixels);
 
-    oklab_image
+    pixels
         .iter()
-        .filter(|pixel| pixel.chroma >= avg_chroma)
+        .filter(|pixel| (MIN_CHROMA..avg_chroma).contains(&pixel.chroma))
         .into_grouping_map_by(|pixel| {
-            // Map each pixel to a segment of the color wheel base on hue
             pixel
                 .hue
                 .into_positive_degrees()
                 .div(360.0 / settings.segment_size)
                 .floor() as u16
         })
-        .fold((0, 0.0, 0.0, 0.0), |(count, l, c, h), _, pixel| {
-            (
-                count + 1,
-                l + pixel.l,
-                c + pixel.chroma,
-                h + pixel.hue.into_positive_degrees(),
-            )
+        .fold((0, 0.0), |(count, h), _, pixel| {
+            (count + 1, h + pixel.hue.into_positive_degrees())
         })
         .values()
-        .sorted_unstable_by_key(|(count, _, _, _)| count)
-        .rev()
-        .map(|(count, l, c, h)| {
-            let count = *count as f64;
-            Oklch::new(l / count, c / count, h / count)
-        })
-        .collect::<Vec<_>>()
+        .max_by_key(|(count, _)| *count)
+        .map(|(count, h)| h / *count as f64)
+}
+
+/// The prominent colors of an image (most prominent first), extracted with the method
+/// chosen in `settings`
+pub fn parse_colors(
+    image: &mut RgbImage,
+    settings: &ParseColorsSettings,
+) -> Result<Vec<Oklch<f64>>> {
+    settings.validate()?;
+    Ok(settings
+        .extractor()
+        .extract(image)?
+        .into_iter()
+        .map(|weighted| weighted.color)
+        .collect())
 }
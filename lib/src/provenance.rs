@@ -0,0 +1,99 @@
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::fmt::Write;
+
+use crate::base24::PaletteSettings;
+use crate::settings::{palette_from_map, yaml_to_json};
+use crate::{ParseColorsSettings, VERSION};
+
+/// A record of how a scheme was generated, so that it can be reproduced later
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Provenance {
+    /// Where the source image was loaded from (if known)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_image: Option<String>,
+    /// The SHA-256 hash of the source image file
+    pub source_hash: String,
+    pub iro_version: String,
+    /// Settings missing from schemes made by older versions are taken from the style's preset
+    #[serde(deserialize_with = "palette_settings_with_preset")]
+    pub palette_settings: PaletteSettings,
+    pub parse_colors_settings: ParseColorsSettings,
+    /// For one side of a dark and light pair, the settings the pair was generated from
+    /// (whose style is the side the hues were taken from)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pair_settings: Option<PaletteSettings>,
+}
+
+impl Provenance {
+    pub fn new(
+        image_bytes: &[u8],
+        source_image: Option<String>,
+        palette_settings: PaletteSettings,
+        parse_colors_settings: ParseColorsSettings,
+    ) -> Self {
+        Self {
+            source_image,
+            source_hash: hash_image(image_bytes),
+            iro_version: VERSION.to_string(),
+            palette_settings,
+            parse_colors_settings,
+            pair_settings: None,
+        }
+    }
+
+    /// Records that the scheme is one side of a pair generated from `pair_settings`
+    pub fn with_pair(self, pair_settings: PaletteSettings) -> Self {
+        Self {
+            pair_settings: Some(pair_settings),
+            ..self
+        }
+    }
+
+    /// Whether `image_bytes` are the same image the scheme was generated from
+    pub fn matches(&self, image_bytes: &[u8]) -> bool {
+        self.source_hash == hash_image(image_bytes)
+    }
+}
+
+fn palette_settings_with_preset<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<PaletteSettings, D::Error> {
+    let value =
+        yaml_to_json(serde_yaml::Value::deserialize(deserializer)?).map_err(D::Error::custom)?;
+    let serde_json::Value::Object(palette) = value else {
+        return Err(D::Error::custom("expected a table of palette settings"));
+    };
+    palette_from_map(palette, None).map_err(D::Error::custom)
+}
+
+/// Hex-encoded SHA-256 hash of an image file
+pub fn hash_image(image_bytes: &[u8]) -> String {
+    Sha256::digest(image_bytes)
+        .iter()
+        .fold(String::new(), |mut hash, byte| {
+            let _ = write!(hash, "{byte:02x}");
+            hash
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base24::PaletteStyle;
+
+    #[test]
+    fn older_provenance_takes_missing_settings_from_the_preset() {
+        let yaml = "source_hash: abc\n\
+                    iro_version: 0.1.0\n\
+                    palette_settings:\n  style: light\n  keep: 4\n\
+                    parse_colors_settings:\n  segment_size: 15.0\n";
+        let provenance: Provenance = serde_yaml::from_str(yaml).unwrap();
+        let preset = PaletteSettings::default_for(PaletteStyle::Light);
+        assert_eq!(provenance.palette_settings.keep, 4);
+        assert_eq!(provenance.palette_settings.ramp, preset.ramp);
+        assert_eq!(provenance.palette_settings.brights, preset.brights);
+        assert!(provenance.pair_settings.is_none());
+    }
+}
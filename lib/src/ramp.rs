@@ -0,0 +1,148 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::base24::PaletteSettings;
+use crate::base24::PaletteStyle;
+use crate::error::{check_range, IroError, Result};
+
+/// How lightness is distributed between the start and the end of the base ramp
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Small steps near the background, large steps near the foreground
+    EaseIn,
+    /// Large steps near the background, small steps near the foreground
+    EaseOut,
+    /// Positions (0 = start, 1 = end) along the ramp, linearly interpolated between
+    Custom(Vec<f64>),
+}
+
+impl Easing {
+    /// Checks that a custom easing has enough positions
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Self::Custom(positions) if positions.len() < 2 => Err(IroError::InvalidSettings(
+                "a custom easing needs at least 2 positions".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Where `t` (0 to 1) lands between the start and the end of the ramp
+    fn apply(&self, t: f64) -> f64 {
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t.powi(2),
+            Self::EaseOut => 1.0 - (1.0 - t).powi(2),
+            Self::Custom(positions) => {
+                let x = t * (positions.len() - 1) as f64;
+                let i = (x.floor() as usize).min(positions.len() - 2);
+                let (a, b) = (positions[i], positions[i + 1]);
+                a + (b - a) * (x - i as f64)
+            }
+        }
+    }
+}
+
+impl Display for Easing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Linear => write!(f, "linear"),
+            Self::EaseIn => write!(f, "ease-in"),
+            Self::EaseOut => write!(f, "ease-out"),
+            Self::Custom(positions) => write!(f, "{}", positions.iter().join(",")),
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = IroError;
+
+    /// Parses `linear`, `ease-in`, `ease-out` or a comma-separated list of positions
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "linear" => Self::Linear,
+            "ease-in" => Self::EaseIn,
+            "ease-out" => Self::EaseOut,
+            _ => Self::Custom(
+                s.split(',')
+                    .map(|position| position.trim().parse::<f64>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| {
+                        IroError::InvalidSettings(format!(
+                            "expected linear, ease-in, ease-out or positions, got {s:?}"
+                        ))
+                    })?,
+            ),
+        })
+    }
+}
+
+/// The lightness (and chroma) of the base colors from base00 to base07
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LightnessRamp {
+    /// The lightness of base00
+    pub start: f64,
+    /// The lightness of base07
+    pub end: f64,
+    pub easing: Easing,
+    /// How much of the base chroma is removed by base07 (0 = none, 1 = all)
+    pub chroma_taper: f64,
+}
+
+impl LightnessRamp {
+    pub fn default_for(style: PaletteStyle) -> Self {
+        let (start, end) = match style {
+            PaletteStyle::Dark | PaletteStyle::Auto => (0.125, 1.0),
+            PaletteStyle::Light => (1.0, 0.125),
+            PaletteStyle::Dim => (0.25, 0.9),
+            PaletteStyle::HighContrast => (0.0, 1.0),
+        };
+        Self {
+            start,
+            end,
+            easing: Easing::Linear,
+            chroma_taper: 0.0,
+        }
+    }
+
+    /// Checks that every setting is within its valid range
+    pub fn validate(&self) -> Result<()> {
+        check_range("ramp start", self.start, &PaletteSettings::LIGHTNESS)?;
+        check_range("ramp end", self.end, &PaletteSettings::LIGHTNESS)?;
+        check_range(
+            "chroma_taper",
+            self.chroma_taper,
+            &PaletteSettings::FRACTION,
+        )?;
+        self.easing.validate()
+    }
+
+    /// The lightness of each base color
+    pub fn lightnesses(&self) -> Result<[f64; 8]> {
+        self.easing.validate()?;
+        let mut lightnesses = [0.0; 8];
+        for (i, lightness) in lightnesses.iter_mut().enumerate() {
+            let t = self.easing.apply(i as f64 / 7.0);
+            *lightness = (self.start + (self.end - self.start) * t).clamp(0.0, 1.0);
+        }
+        Ok(lightnesses)
+    }
+
+    /// The chroma of the `i`th base color
+    pub fn chroma(&self, base_chroma: f64, i: usize) -> f64 {
+        base_chroma * (1.0 - self.chroma_taper * i as f64 / 7.0)
+    }
+}
+
+impl Default for LightnessRamp {
+    fn default() -> Self {
+        Self::default_for(PaletteStyle::Dark)
+    }
+}
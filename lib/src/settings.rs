@@ -0,0 +1,420 @@
+use palette::Oklch;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::base24::{BackgroundTint, PaletteSettings, PaletteStyle};
+use crate::contrast::ContrastMetric;
+use crate::error::{IroError, Result};
+use crate::highlights::{BrightDerivation, HighlightLightness};
+use crate::hues::{HueAssignment, HueFill};
+use crate::ramp::{Easing, LightnessRamp};
+use crate::ParseColorsSettings;
+
+/// Everything needed to generate a scheme from an image, as stored in settings files
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Settings {
+    pub palette: PaletteSettings,
+    pub parse_colors: ParseColorsSettings,
+}
+
+impl Settings {
+    /// Checks that every setting is within its valid range
+    pub fn validate(&self) -> Result<()> {
+        self.palette.validate()?;
+        self.parse_colors.validate()
+    }
+
+    /// Loads (possibly partial) settings. Missing palette settings are taken from the preset
+    /// of the given style (or the dark preset), using the preset an auto style resolves to
+    /// for `image_lightness` if it is known (the style itself stays auto).
+    pub fn from_value(value: Value, image_lightness: Option<f64>) -> Result<Self> {
+        let Value::Object(mut value) = value else {
+            return Err(IroError::InvalidSettings(
+                "expected a table of settings".to_string(),
+            ));
+        };
+        let palette = palette_from_map(section(&mut value, "palette")?, image_lightness)?;
+        let Value::Object(mut parse_colors) = serde_json::to_value(ParseColorsSettings::default())?
+        else {
+            unreachable!("settings serialize to an object");
+        };
+        parse_colors.extend(section(&mut value, "parse_colors")?);
+        if let Some(key) = value.keys().next() {
+            return Err(IroError::InvalidSettings(format!(
+                "unknown settings section {key:?} (expected palette or parse_colors)"
+            )));
+        }
+        let settings = Self {
+            palette,
+            parse_colors: ParseColorsSettings::deserialize(Value::Object(parse_colors))
+                .map_err(invalid)?,
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn from_json(json: &str, image_lightness: Option<f64>) -> Result<Self> {
+        let value = serde_json::from_str(json)
+            .map_err(|error| IroError::InvalidSettings(error.to_string()))?;
+        Self::from_value(value, image_lightness)
+    }
+
+    pub fn from_toml(toml: &str, image_lightness: Option<f64>) -> Result<Self> {
+        let value =
+            toml::from_str(toml).map_err(|error| IroError::InvalidSettings(error.to_string()))?;
+        Self::from_value(value, image_lightness)
+    }
+
+    pub fn from_yaml(yaml: &str, image_lightness: Option<f64>) -> Result<Self> {
+        let value = serde_yaml::from_str(yaml)
+            .map_err(|error| IroError::InvalidSettings(error.to_string()))?;
+        Self::from_value(yaml_to_json(value)?, image_lightness)
+    }
+
+    pub fn to_json(&self, image_lightness: Option<f64>) -> Result<String> {
+        Ok(serde_json::to_string_pretty(
+            &self.to_value(image_lightness)?,
+        )?)
+    }
+
+    pub fn to_toml(&self, image_lightness: Option<f64>) -> Result<String> {
+        let mut value = self.to_value(image_lightness)?;
+        // TOML has no null, and missing settings load as their preset's anyway
+        if let Some(Value::Object(palette)) = value.get_mut("palette") {
+            palette.retain(|_, value| !value.is_null());
+        }
+        Ok(toml::to_string_pretty(&value)?)
+    }
+
+    /// The settings as a table, leaving out palette settings that match the preset of their
+    /// style (resolved for `image_lightness`) so that they follow the preset when loaded.
+    /// This keeps auto styles portable between images.
+    fn to_value(&self, image_lightness: Option<f64>) -> Result<Value> {
+        let style = match image_lightness {
+            Some(lightness) => self.palette.style.resolve(lightness),
+            None => self.palette.style,
+        };
+        let mut value = serde_json::to_value(self)?;
+        let preset = serde_json::to_value(PaletteSettings::default_for(style))?;
+        if let (Some(Value::Object(palette)), Value::Object(preset)) =
+            (value.get_mut("palette"), preset)
+        {
+            without_preset(palette, &preset);
+            // The style picks the preset, so it is always kept
+            palette.insert(
+                "style".to_string(),
+                serde_json::to_value(self.palette.style)?,
+            );
+        }
+        Ok(value)
+    }
+}
+
+/// Removes the settings (and nested settings) that are the same as in `preset`
+fn without_preset(settings: &mut Map<String, Value>, preset: &Map<String, Value>) {
+    settings.retain(|key, value| match (value, preset.get(key)) {
+        (Value::Object(fields), Some(Value::Object(preset))) => {
+            without_preset(fields, preset);
+            !fields.is_empty()
+        }
+        (value, preset) => preset != Some(value),
+    });
+}
+
+/// Converts YAML to JSON, turning tagged values (which is how YAML writes enums with data,
+/// e.g. `!scale`) into single-key maps
+pub(crate) fn yaml_to_json(value: serde_yaml::Value) -> Result<Value> {
+    fn untag(value: serde_yaml::Value) -> serde_yaml::Value {
+        match value {
+            serde_yaml::Value::Tagged(tagged) => {
+                let tag = tagged.tag.to_string();
+                let mut map = serde_yaml::Mapping::new();
+                map.insert(tag.trim_start_matches('!').into(), untag(tagged.value));
+                serde_yaml::Value::Mapping(map)
+            }
+            serde_yaml::Value::Mapping(map) => serde_yaml::Value::Mapping(
+                map.into_iter()
+                    .map(|(key, value)| (key, untag(value)))
+                    .collect(),
+            ),
+            serde_yaml::Value::Sequence(values) => {
+                serde_yaml::Value::Sequence(values.into_iter().map(untag).collect())
+            }
+            value => value,
+        }
+    }
+    serde_json::to_value(untag(value)).map_err(invalid)
+}
+
+fn invalid(error: serde_json::Error) -> IroError {
+    IroError::InvalidSettings(error.to_string())
+}
+
+/// Removes the table `name` from `value` (or an empty one if it is missing)
+fn section(value: &mut Map<String, Value>, name: &str) -> Result<Map<String, Value>> {
+    match value.remove(name) {
+        None => Ok(Map::new()),
+        Some(Value::Object(section)) => Ok(section),
+        Some(_) => Err(IroError::InvalidSettings(format!(
+            "expected {name} to be a table of settings"
+        ))),
+    }
+}
+
+/// Palette settings from a (possibly partial) table, with missing fields taken from the
+/// preset of its style. An auto style takes the preset it resolves to for `image_lightness`
+/// (if known), but stays auto.
+pub(crate) fn palette_from_map(
+    mut palette: Map<String, Value>,
+    image_lightness: Option<f64>,
+) -> Result<PaletteSettings> {
+    let style = palette
+        .get("style")
+        .map(|style| PaletteStyle::deserialize(style).map_err(invalid))
+        .transpose()?
+        .unwrap_or_default();
+    let preset = match image_lightness {
+        Some(lightness) => style.resolve(lightness),
+        None => style,
+    };
+    let Value::Object(mut merged) = serde_json::to_value(PaletteSettings::default_for(preset))?
+    else {
+        unreachable!("settings serialize to an object");
+    };
+
+    // Merge field by field so that a partial ramp keeps the rest of the preset's ramp
+    if let Some(Value::Object(ramp)) = palette.remove("ramp") {
+        if let Some(Value::Object(merged_ramp)) = merged.get_mut("ramp") {
+            merged_ramp.extend(ramp);
+        }
+    }
+    merged.extend(palette);
+    PaletteSettings::deserialize(Value::Object(merged)).map_err(invalid)
+}
+
+/// Builds [`PaletteSettings`] on top of a style's preset, checking them once they are built
+#[derive(Debug, Clone)]
+pub struct PaletteSettingsBuilder {
+    settings: PaletteSettings,
+}
+
+impl PaletteSettingsBuilder {
+    pub fn new(style: PaletteStyle) -> Self {
+        Self {
+            settings: PaletteSettings::default_for(style),
+        }
+    }
+
+    /// Changes the style without loading its preset, e.g. to keep an auto style that the
+    /// builder was started with the resolved preset of
+    pub fn style(mut self, style: PaletteStyle) -> Self {
+        self.settings.style = style;
+        self
+    }
+
+    pub fn keep(mut self, keep: usize) -> Self {
+        self.settings.keep = keep;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: usize) -> Self {
+        self.settings.rotation = rotation;
+        self
+    }
+
+    pub fn hue_assignment(mut self, hue_assignment: HueAssignment) -> Self {
+        self.settings.hue_assignment = hue_assignment;
+        self
+    }
+
+    pub fn hue_fill(mut self, hue_fill: HueFill) -> Self {
+        self.settings.hue_fill = hue_fill;
+        self
+    }
+
+    pub fn base_chroma(mut self, base_chroma: f64) -> Self {
+        self.settings.base_chroma = base_chroma;
+        self
+    }
+
+    pub fn background_tint(mut self, background_tint: BackgroundTint) -> Self {
+        self.settings.background_tint = background_tint;
+        self
+    }
+
+    pub fn ramp(mut self, ramp: LightnessRamp) -> Self {
+        self.settings.ramp = ramp;
+        self
+    }
+
+    pub fn ramp_start(mut self, start: f64) -> Self {
+        self.settings.ramp.start = start;
+        self
+    }
+
+    pub fn ramp_end(mut self, end: f64) -> Self {
+        self.settings.ramp.end = end;
+        self
+    }
+
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.settings.ramp.easing = easing;
+        self
+    }
+
+    pub fn chroma_taper(mut self, chroma_taper: f64) -> Self {
+        self.settings.ramp.chroma_taper = chroma_taper;
+        self
+    }
+
+    pub fn hl_lightness(mut self, hl_lightness: f64) -> Self {
+        self.settings.hl_lightness = hl_lightness;
+        self
+    }
+
+    pub fn hl_lightness_mode(mut self, hl_lightness_mode: HighlightLightness) -> Self {
+        self.settings.hl_lightness_mode = hl_lightness_mode;
+        self
+    }
+
+    pub fn hl_lightness_spread(mut self, hl_lightness_spread: f64) -> Self {
+        self.settings.hl_lightness_spread = hl_lightness_spread;
+        self
+    }
+
+    pub fn hl_chroma(mut self, hl_chroma: f64) -> Self {
+        self.settings.hl_chroma = hl_chroma;
+        self
+    }
+
+    pub fn min_hue_separation(mut self, min_hue_separation: Option<f64>) -> Self {
+        self.settings.min_hue_separation = min_hue_separation;
+        self
+    }
+
+    pub fn min_delta_e(mut self, min_delta_e: Option<f64>) -> Self {
+        self.settings.min_delta_e = min_delta_e;
+        self
+    }
+
+    pub fn fidelity(mut self, fidelity: f64) -> Self {
+        self.settings.fidelity = fidelity;
+        self
+    }
+
+    pub fn brights(mut self, brights: BrightDerivation) -> Self {
+        self.settings.brights = brights;
+        self
+    }
+
+    pub fn bright_chroma(mut self, bright_chroma: Option<f64>) -> Self {
+        self.settings.bright_chroma = bright_chroma;
+        self
+    }
+
+    pub fn min_contrast(mut self, min_contrast: Option<f64>) -> Self {
+        self.settings.min_contrast = min_contrast;
+        self
+    }
+
+    pub fn contrast_metric(mut self, contrast_metric: ContrastMetric) -> Self {
+        self.settings.contrast_metric = contrast_metric;
+        self
+    }
+
+    pub fn cvd_min_delta_e(mut self, cvd_min_delta_e: Option<f64>) -> Self {
+        self.settings.cvd_min_delta_e = cvd_min_delta_e;
+        self
+    }
+
+    /// Locks `slot` to `color`
+    pub fn override_slot(mut self, slot: usize, color: Oklch<f64>) -> Self {
+        self.settings.overrides.insert(slot, color);
+        self
+    }
+
+    pub fn build(self) -> Result<PaletteSettings> {
+        self.settings.validate()?;
+        Ok(self.settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_ramp_keeps_the_rest_of_the_preset_ramp() {
+        let settings = Settings::from_toml("[palette.ramp]\nstart = 0.2\n", None).unwrap();
+        let preset = PaletteSettings::default_for(PaletteStyle::Dark).ramp;
+        assert_eq!(settings.palette.ramp.start, 0.2);
+        assert_eq!(settings.palette.ramp.end, preset.end);
+        assert_eq!(settings.palette.ramp.easing, preset.easing);
+    }
+
+    #[test]
+    fn missing_settings_come_from_the_style_preset() {
+        let settings = Settings::from_json(r#"{"palette": {"style": "light"}}"#, None).unwrap();
+        let preset = PaletteSettings::default_for(PaletteStyle::Light);
+        assert_eq!(settings.palette.ramp, preset.ramp);
+        assert_eq!(settings.palette.brights, preset.brights);
+    }
+
+    #[test]
+    fn auto_styles_take_the_preset_they_resolve_to() {
+        let settings = Settings::from_yaml("palette:\n  style: auto\n", Some(0.7)).unwrap();
+        assert_eq!(settings.palette.style, PaletteStyle::Auto);
+        assert_eq!(settings.palette.style.resolve(0.7), PaletteStyle::Light);
+        let preset = PaletteSettings::default_for(PaletteStyle::Light);
+        assert_eq!(settings.palette.ramp, preset.ramp);
+    }
+
+    #[test]
+    fn yaml_tagged_enums_round_trip() {
+        let brights = BrightDerivation::Scale {
+            lightness: 1.5,
+            chroma: 0.8,
+        };
+        let settings = Settings {
+            palette: PaletteSettings::builder(PaletteStyle::Dark)
+                .brights(brights)
+                .build()
+                .unwrap(),
+            ..Default::default()
+        };
+        let yaml = serde_yaml::to_string(&settings).unwrap();
+        assert!(yaml.contains("!scale"));
+        assert_eq!(
+            Settings::from_yaml(&yaml, None).unwrap().palette.brights,
+            brights
+        );
+    }
+
+    #[test]
+    fn saved_settings_leave_out_the_preset() {
+        let settings = Settings {
+            palette: PaletteSettings::builder(PaletteStyle::Light)
+                .style(PaletteStyle::Auto)
+                .hl_chroma(0.2)
+                .build()
+                .unwrap(),
+            ..Default::default()
+        };
+        let toml = settings.to_toml(Some(0.7)).unwrap();
+        assert_eq!(
+            toml.lines().take(3).collect::<Vec<_>>(),
+            ["[palette]", "hl_chroma = 0.2", "style = \"auto\""]
+        );
+        // On a dark image, the same file picks the dark preset
+        let loaded = Settings::from_toml(&toml, Some(0.2)).unwrap();
+        let preset = PaletteSettings::default_for(PaletteStyle::Dark);
+        assert_eq!(loaded.palette.ramp, preset.ramp);
+        assert_eq!(loaded.palette.hl_chroma, 0.2);
+    }
+
+    #[test]
+    fn unknown_settings_are_rejected() {
+        assert!(Settings::from_toml("[palette]\nhl_chroam = 0.3\n", None).is_err());
+        assert!(Settings::from_toml("[parse_colour]\nsegment_size = 30\n", None).is_err());
+    }
+}
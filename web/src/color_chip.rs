@@ -0,0 +1,62 @@
+use iro::{hex_to_lch, lch_to_hex, Oklch};
+use leptos::*;
+
+use std::collections::BTreeMap;
+
+/// A palette swatch that opens a color picker to lock its slot to a chosen color
+#[component]
+pub fn ColorChip(
+    index: usize,
+    color: Oklch<f64>,
+    displayed: Oklch<f64>,
+    overrides: RwSignal<BTreeMap<usize, Oklch<f64>>>,
+) -> impl IntoView {
+    let name = format!("base{index:02X}");
+    let style = format!("background-color: #{};", lch_to_hex(&displayed));
+    let locked = move || overrides.with(|overrides| overrides.contains_key(&index));
+    let pick = move |ev| {
+        if let Ok(color) = hex_to_lch(&event_target_value(&ev)) {
+            overrides.update(|overrides| {
+                overrides.insert(index, color);
+            });
+        }
+    };
+    let unlock = move |_| {
+        overrides.update(|overrides| {
+            overrides.remove(&index);
+        });
+    };
+    view! {
+        <div class="relative size-12">
+            <label title=format!("Pick a color for {name}") class="cursor-pointer">
+                <div class="rounded-full size-12" style=style></div>
+                <input
+                    type="color"
+                    on:change=pick
+                    value=format!("#{}", lch_to_hex(&color))
+                    class="absolute invisible size-0"
+                />
+            </label>
+            <Show when=locked>
+                <button
+                    title=format!("Unlock {name}")
+                    on:click=unlock
+                    class="absolute -top-1 -right-1"
+                >
+                    <svg
+                        xmlns="http://www.w3.org/2000/svg"
+                        viewBox="0 0 24 24"
+                        fill="currentColor"
+                        class="w-4 h-4"
+                    >
+                        <path
+                            fill-rule="evenodd"
+                            d="M12 1.5a5.25 5.25 0 0 0-5.25 5.25v3a3 3 0 0 0-3 3v6.75a3 3 0 0 0 3 3h10.5a3 3 0 0 0 3-3v-6.75a3 3 0 0 0-3-3v-3c0-2.9-2.35-5.25-5.25-5.25Zm3.75 8.25v-3a3.75 3.75 0 1 0-7.5 0v3h7.5Z"
+                            clip-rule="evenodd"
+                        ></path>
+                    </svg>
+                </button>
+            </Show>
+        </div>
+    }
+}
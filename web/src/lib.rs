@@ -1,59 +1,96 @@
 mod backdrop;
 mod code_preview;
+mod color_chip;
 mod copy_button;
 mod image_upload;
+mod select;
 mod toggle;
 mod value_slider;
 
 use crate::backdrop::Backdrop;
 use crate::code_preview::CodePreview;
+use crate::color_chip::ColorChip;
 use crate::copy_button::CopyButton;
 use crate::image_upload::ImageUpload;
+use crate::select::Select;
 use crate::toggle::Toggle;
 use crate::value_slider::ValueSlider;
 use iro::base24::PaletteStyle;
 
-use anyhow::Result;
-use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
-use image::imageops::FilterType::Nearest;
-use image::io::Reader as ImageReader;
-use iro::base24::PaletteSettings;
-use iro::{generate_palette, lch_to_hex, parse_colors, Base24Style, Oklch, ParseColorsSettings};
+use base64::engine::general_purpose::{STANDARD as BASE64_STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+use image::imageops::{resize, FilterType::Nearest};
+use image::RgbImage;
+use iro::base24::{neutral_palette, BackgroundTint, PaletteSettings};
+use iro::contrast::ContrastMetric;
+use iro::cvd::{simulate_palette, Deficiency};
+use iro::extractor::ExtractionMethod;
+use iro::generator::{Base24Generator, PaletteGenerator};
+use iro::highlights::{BrightDerivation, HighlightLightness};
+use iro::hues::{HueAssignment, HueFill};
+use iro::ramp::Easing;
+use iro::settings::Settings;
+use iro::{
+    decode_image, lch_to_hex, mean_lightness, parse_colors, Base24Style, Oklch,
+    ParseColorsSettings, Result,
+};
+use leptos::wasm_bindgen::JsValue;
 use leptos::*;
 
-use std::io::Cursor;
+use std::iter;
+
+fn load_image(image_bytes: &[u8]) -> Result<RgbImage> {
+    let full_img = decode_image(image_bytes)?;
+    Ok(resize(
+        &full_img,
+        full_img.width() / 4,
+        full_img.height() / 4,
+        Nearest,
+    ))
+}
 
 fn colors_from_image(
     image_bytes: &[u8],
     parse_colors_settings: &ParseColorsSettings,
 ) -> Result<Vec<Oklch<f64>>> {
-    let full_img = ImageReader::new(Cursor::new(image_bytes))
-        .with_guessed_format()?
-        .decode()?;
-    let mut img = full_img
-        .resize(full_img.width() / 4, full_img.height() / 4, Nearest)
-        .to_rgb8();
-    Ok(parse_colors(&mut img, parse_colors_settings))
+    let mut img = load_image(image_bytes)?;
+    parse_colors(&mut img, parse_colors_settings)
 }
 
-fn style_from_colors(
-    colors: Vec<Oklch<f64>>,
+fn style_from_palette(
+    palette: [Oklch<f64>; 24],
     palette_settings: &PaletteSettings,
-) -> Result<Base24Style> {
-    let palette = generate_palette(colors, palette_settings)?;
-    Ok(Base24Style {
+) -> Base24Style {
+    Base24Style {
         name: "Test Style".to_string(),
         author: "".to_string(),
         variant: palette_settings.style.to_string(),
         palette,
-    })
+        provenance: None,
+    }
 }
 
-#[component]
-pub fn ColorChip(color: Oklch<f64>) -> impl IntoView {
-    let hex = lch_to_hex(&color);
-    let style = format!("background-color: #{hex};");
-    view! { <div class="rounded-full size-12" style=style></div> }
+/// Settings stored in the URL fragment, so that a configuration can be shared as a link.
+/// An auto style takes the preset for the default image, which is the one shown first.
+fn settings_from_url() -> Option<Settings> {
+    let fragment = window().location().hash().ok()?;
+    let json = URL_SAFE_NO_PAD
+        .decode(fragment.trim_start_matches('#'))
+        .ok()?;
+    let image_lightness = load_image(DEFAULT_IMAGE)
+        .ok()
+        .map(|img| mean_lightness(&img));
+    Settings::from_json(std::str::from_utf8(&json).ok()?, image_lightness).ok()
+}
+
+fn settings_to_url(settings: &Settings, image_lightness: f64) {
+    let Ok(json) = settings.to_json(Some(image_lightness)) else {
+        return;
+    };
+    let fragment = format!("#{}", URL_SAFE_NO_PAD.encode(json));
+    if let Ok(history) = window().history() {
+        let _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&fragment));
+    }
 }
 
 static DEFAULT_IMAGE: &[u8] = include_bytes!("../static/shirasuka-shiomi-slope.png");
@@ -64,48 +101,187 @@ pub fn ImagePreview() -> impl IntoView {
     let (image_bytes, set_image_bytes) = create_signal::<Box<[u8]>>(DEFAULT_IMAGE.into());
     let base64_data = move || BASE64_STANDARD.encode(image_bytes());
 
-    let default_parse_settings = ParseColorsSettings::default();
-    let default_settings = PaletteSettings::default();
+    let Settings {
+        palette: initial_settings,
+        parse_colors: initial_parse_settings,
+    } = settings_from_url().unwrap_or_default();
 
-    let segment_size = create_rw_signal(default_parse_settings.segment_size);
-    let dark_mode = create_rw_signal(default_settings.style == PaletteStyle::Dark);
-    let keep = create_rw_signal(8);
-    let rotation = create_rw_signal(0);
-    let base_chroma = create_rw_signal(default_settings.base_chroma);
-    let hl_chroma = create_rw_signal(default_settings.hl_chroma);
-    let hl_lightness = create_rw_signal(default_settings.hl_lightness);
+    let extraction_method = create_rw_signal(initial_parse_settings.method);
+    let segment_size = create_rw_signal(initial_parse_settings.segment_size);
+    let clusters = create_rw_signal(initial_parse_settings.clusters);
+    let style = create_rw_signal(initial_settings.style);
+    let keep = create_rw_signal(initial_settings.keep);
+    let rotation = create_rw_signal(initial_settings.rotation);
+    let semantic_hues =
+        create_rw_signal(initial_settings.hue_assignment == HueAssignment::Semantic);
+    let hue_fill = create_rw_signal(initial_settings.hue_fill);
+    let base_chroma = create_rw_signal(initial_settings.base_chroma);
+    let background = create_rw_signal(match initial_settings.background_tint {
+        BackgroundTint::Accent => "accent",
+        BackgroundTint::Hue(_) => "hue",
+        BackgroundTint::Neutral => "neutral",
+        BackgroundTint::Image => "image",
+    });
+    let tint_hue = create_rw_signal(match initial_settings.background_tint {
+        BackgroundTint::Hue(hue) => hue,
+        _ => 0.0,
+    });
+    let ramp_start = create_rw_signal(initial_settings.ramp.start);
+    let ramp_end = create_rw_signal(initial_settings.ramp.end);
+    let easing = create_rw_signal(initial_settings.ramp.easing.clone());
+    let chroma_taper = create_rw_signal(initial_settings.ramp.chroma_taper);
+    let hl_chroma = create_rw_signal(initial_settings.hl_chroma);
+    let hl_lightness = create_rw_signal(initial_settings.hl_lightness);
+    let hl_lightness_mode = create_rw_signal(initial_settings.hl_lightness_mode);
+    let min_hue_separation = create_rw_signal(initial_settings.min_hue_separation.unwrap_or(0.0));
+    let fidelity = create_rw_signal(initial_settings.fidelity);
+    let brights = create_rw_signal(initial_settings.brights);
+    // The contrast metric has no control, so the slider covers the loaded metric's range
+    let contrast_metric = initial_settings.contrast_metric;
+    let no_contrast = *contrast_metric.range().start();
+    let min_contrast = create_rw_signal(initial_settings.min_contrast.unwrap_or(no_contrast));
+    let cvd_min_delta_e = create_rw_signal(initial_settings.cvd_min_delta_e.unwrap_or(0.0));
+    let simulate = create_rw_signal::<Option<Deficiency>>(None);
+    let overrides = create_rw_signal(initial_settings.overrides.clone());
 
     let parse_colors_settings = move || ParseColorsSettings {
+        method: extraction_method(),
         segment_size: segment_size.get(),
+        clusters: clusters(),
     };
 
-    let palette_settings = move || PaletteSettings {
-        style: match dark_mode() {
-            true => PaletteStyle::Dark,
-            false => PaletteStyle::Light,
-        },
-        keep: keep(),
-        rotation: rotation(),
-        base_chroma: base_chroma(),
-        hl_lightness: hl_lightness(),
-        hl_chroma: hl_chroma(),
+    let image = create_memo(move |_| load_image(&image_bytes()).ok());
+    let image_lightness = create_memo(move |_| {
+        image.with(|img| img.as_ref().map(mean_lightness).unwrap_or_default())
+    });
+    let resolved_style = move || style().resolve(image_lightness());
+    let background_tint = move || match background() {
+        "image" => BackgroundTint::Image,
+        "hue" => BackgroundTint::Hue(tint_hue()),
+        "neutral" => BackgroundTint::Neutral,
+        _ => BackgroundTint::Accent,
+    };
+
+    // Settings without a control keep the values they were loaded with. An auto style and
+    // an image background stay unresolved, so that shared links work with other images.
+    let palette_settings = move || {
+        let builder = PaletteSettings::builder(resolved_style())
+            .style(style())
+            .keep(keep())
+            .rotation(rotation())
+            .hue_assignment(match semantic_hues() {
+                true => HueAssignment::Semantic,
+                false => HueAssignment::Rotation,
+            })
+            .hue_fill(hue_fill())
+            .base_chroma(base_chroma())
+            .background_tint(background_tint())
+            .ramp_start(ramp_start())
+            .ramp_end(ramp_end())
+            .easing(easing())
+            .chroma_taper(chroma_taper())
+            .hl_lightness(hl_lightness())
+            .hl_lightness_mode(hl_lightness_mode())
+            .hl_lightness_spread(initial_settings.hl_lightness_spread)
+            .min_hue_separation(Some(min_hue_separation()).filter(|separation| *separation > 0.0))
+            .min_delta_e(initial_settings.min_delta_e)
+            .fidelity(fidelity())
+            .brights(brights())
+            .bright_chroma(initial_settings.bright_chroma)
+            .hl_chroma(hl_chroma())
+            .min_contrast(Some(min_contrast()).filter(|contrast| *contrast > no_contrast))
+            .contrast_metric(contrast_metric)
+            .cvd_min_delta_e(Some(cvd_min_delta_e()).filter(|min_delta_e| *min_delta_e > 0.0));
+        overrides()
+            .into_iter()
+            .fold(builder, |builder, (slot, color)| {
+                builder.override_slot(slot, color)
+            })
+            .build()
     };
-    let image_colors = create_memo(move |_| {
-        colors_from_image(&image_bytes(), &parse_colors_settings())
-            .expect("colors extracted successfully")
+    // Switching styles loads that style's preset into the controls (but the initial style
+    // keeps the settings it was loaded with)
+    create_effect(move |previous_style| {
+        let style = resolved_style();
+        if previous_style.is_some_and(|previous_style| previous_style != style) {
+            let defaults = PaletteSettings::default_for(style);
+            base_chroma.set(defaults.base_chroma);
+            ramp_start.set(defaults.ramp.start);
+            ramp_end.set(defaults.ramp.end);
+            hl_chroma.set(defaults.hl_chroma);
+            hl_lightness.set(defaults.hl_lightness);
+            brights.set(defaults.brights);
+            // Preset contrasts are measured with the preset's metric
+            min_contrast.set(
+                defaults
+                    .min_contrast
+                    .filter(|_| defaults.contrast_metric == contrast_metric)
+                    .unwrap_or(no_contrast),
+            );
+        }
+        style
+    });
+    // The settings for the current image
+    let resolved_settings = move || {
+        palette_settings().map(|settings| {
+            image.with(|img| match img {
+                Some(img) => settings.resolve(img, &parse_colors_settings()),
+                None => settings,
+            })
+        })
+    };
+    let settings = move || {
+        palette_settings().map(|palette| Settings {
+            palette,
+            parse_colors: parse_colors_settings(),
+        })
+    };
+    create_effect(move |_| {
+        if let Ok(settings) = settings() {
+            settings_to_url(&settings, image_lightness());
+        }
+    });
+    let settings_json = Signal::derive(move || {
+        settings()
+            .and_then(|settings| settings.to_json(Some(image_lightness())))
+            .unwrap_or_default()
+    });
+    let image_colors =
+        create_memo(move |_| colors_from_image(&image_bytes(), &parse_colors_settings()));
+    let palette = create_memo(move |_| {
+        let settings = resolved_settings()?;
+        image_colors().and_then(|colors| Base24Generator::new(settings).generate(colors))
+    });
+    let error = move || palette().err().map(|error| error.to_string());
+    // Fall back to a neutral scheme (or, failing that, plain gray) so the app stays usable
+    let b24_style = Signal::derive(move || {
+        let settings = resolved_settings().unwrap_or_default();
+        let palette = palette()
+            .or_else(|_| neutral_palette(&settings))
+            .unwrap_or([Oklch::new(0.5, 0.0, 0.0); 24]);
+        style_from_palette(palette, &settings)
+    });
+    // The style as it is displayed, which may be simulating a color-vision deficiency
+    let display_style = Signal::derive(move || {
+        let style = b24_style();
+        match simulate() {
+            Some(deficiency) => Base24Style {
+                palette: simulate_palette(&style.palette, deficiency),
+                ..style
+            },
+            None => style,
+        }
     });
-    let b24_style =
-        Signal::derive(move || style_from_colors(image_colors(), &palette_settings()).unwrap());
     let bg_color_style = Signal::derive(move || {
-        let hex = lch_to_hex(&b24_style().palette[0]);
+        let hex = lch_to_hex(&display_style().palette[0]);
         format!("background-color: #{hex};")
     });
     let controls_style = move || {
-        let comment = lch_to_hex(&b24_style().palette[4]);
-        let foreground = lch_to_hex(&b24_style().palette[5]);
+        let comment = lch_to_hex(&display_style().palette[4]);
+        let foreground = lch_to_hex(&display_style().palette[5]);
         let content = format!(
             r##"
-            span, label, svg {{ color: #{foreground}; }}
+            span, label, svg, select {{ color: #{foreground}; }}
             input[type=range]::-webkit-slider-runnable-track {{ background: #{foreground}; }}
             input[type=range]::-webkit-slider-thumb {{ background: #{comment}; }}
             input[type=range]:focus::-webkit-slider-runnable-track {{ background: #{comment}; }}
@@ -125,11 +301,11 @@ pub fn ImagePreview() -> impl IntoView {
         )
     };
     let color_chips = move || {
-        b24_style
-            .get()
-            .palette
-            .into_iter()
-            .map(|color| view! { <ColorChip color=color/> })
+        iter::zip(b24_style().palette, display_style().palette)
+            .enumerate()
+            .map(|(index, (color, displayed))| {
+                view! { <ColorChip index=index color=color displayed=displayed overrides=overrides/> }
+            })
             .collect::<Vec<_>>()
     };
 
@@ -166,14 +342,71 @@ pub fn ImagePreview() -> impl IntoView {
                                         title="Copy YAML colorscheme to clipboard"
                                         content=yaml
                                     />
+                                    <CopyButton
+                                        title="Copy generation settings to clipboard (for iro-cli --settings)"
+                                        content=settings_json
+                                    />
                                 </div>
-                                <Toggle
-                                    signal=dark_mode
-                                    true_label="Dark Mode"
-                                    false_label="Light Mode"
+                                <Select
+                                    name="Style"
+                                    signal=style
+                                    options=[
+                                        PaletteStyle::Dark,
+                                        PaletteStyle::Light,
+                                        PaletteStyle::Dim,
+                                        PaletteStyle::HighContrast,
+                                        PaletteStyle::Auto,
+                                    ]
+                                        .map(|style| (style.to_string(), style))
+                                        .into()
                                 />
                             </div>
-                            <ValueSlider name="Unique Colors" value_signal=keep min=1 max=8 step=1/>
+                            <Show when=move || error().is_some()>
+                                <p class="pb-2" role="alert">
+                                    {error}
+                                </p>
+                            </Show>
+                            <Select
+                                name="Extractor"
+                                signal=extraction_method
+                                options=[ExtractionMethod::HueSegments, ExtractionMethod::KMeans]
+                                    .map(|method| (method.to_string(), method))
+                                    .into()
+                            />
+                            <Show when=move || extraction_method() == ExtractionMethod::KMeans>
+                                <ValueSlider
+                                    name="Clusters"
+                                    value_signal=clusters
+                                    min=*ParseColorsSettings::CLUSTERS.start()
+                                    max=*ParseColorsSettings::CLUSTERS.end()
+                                    step=1
+                                />
+                            </Show>
+                            <ValueSlider
+                                name="Unique Colors"
+                                value_signal=keep
+                                min=*PaletteSettings::KEEP.start()
+                                max=*PaletteSettings::KEEP.end()
+                                step=1
+                            />
+                            <Select
+                                name="Hue Fill"
+                                signal=hue_fill
+                                options=[
+                                    HueFill::Repeat,
+                                    HueFill::Complementary,
+                                    HueFill::Analogous,
+                                    HueFill::Triadic,
+                                    HueFill::Even,
+                                ]
+                                    .map(|fill| (fill.to_string(), fill))
+                                    .into()
+                            />
+                            <Toggle
+                                signal=semantic_hues
+                                true_label="Semantic Hues"
+                                false_label="Rotated Hues"
+                            />
                             <ValueSlider name="Rotate" value_signal=rotation min=0 max=7 step=1/>
                             <ValueSlider
                                 name="Base Chroma"
@@ -182,6 +415,50 @@ pub fn ImagePreview() -> impl IntoView {
                                 max=0.16
                                 step=0.005
                             />
+                            <Select
+                                name="Background"
+                                signal=background
+                                options=["accent", "image", "hue", "neutral"]
+                                    .map(|tint| (tint.to_string(), tint))
+                                    .into()
+                            />
+                            <Show when=move || background() == "hue">
+                                <ValueSlider
+                                    name="Background Hue"
+                                    value_signal=tint_hue
+                                    min=0.0
+                                    max=360.0
+                                    step=5.0
+                                />
+                            </Show>
+                            <ValueSlider
+                                name="Ramp Start"
+                                value_signal=ramp_start
+                                min=*PaletteSettings::LIGHTNESS.start()
+                                max=*PaletteSettings::LIGHTNESS.end()
+                                step=0.025
+                            />
+                            <ValueSlider
+                                name="Ramp End"
+                                value_signal=ramp_end
+                                min=*PaletteSettings::LIGHTNESS.start()
+                                max=*PaletteSettings::LIGHTNESS.end()
+                                step=0.025
+                            />
+                            <Select
+                                name="Easing"
+                                signal=easing
+                                options=[Easing::Linear, Easing::EaseIn, Easing::EaseOut]
+                                    .map(|easing| (easing.to_string(), easing))
+                                    .into()
+                            />
+                            <ValueSlider
+                                name="Chroma Taper"
+                                value_signal=chroma_taper
+                                min=*PaletteSettings::FRACTION.start()
+                                max=*PaletteSettings::FRACTION.end()
+                                step=0.05
+                            />
                             <ValueSlider
                                 name="Highlight Chroma"
                                 value_signal=hl_chroma
@@ -192,15 +469,83 @@ pub fn ImagePreview() -> impl IntoView {
                             <ValueSlider
                                 name="Highlight Lightness"
                                 value_signal=hl_lightness
-                                min=0.0
-                                max=1.0
+                                min=*PaletteSettings::LIGHTNESS.start()
+                                max=*PaletteSettings::LIGHTNESS.end()
                                 step=0.05
                             />
+                            <Select
+                                name="Highlight Lightness Mode"
+                                signal=hl_lightness_mode
+                                options=[
+                                    HighlightLightness::Fixed,
+                                    HighlightLightness::Source,
+                                    HighlightLightness::Apca,
+                                ]
+                                    .map(|mode| (mode.to_string(), mode))
+                                    .into()
+                            />
+                            <ValueSlider
+                                name="Fidelity"
+                                value_signal=fidelity
+                                min=*PaletteSettings::FRACTION.start()
+                                max=*PaletteSettings::FRACTION.end()
+                                step=0.05
+                            />
+                            <ValueSlider
+                                name="Hue Separation"
+                                value_signal=min_hue_separation
+                                min=0.0
+                                max=45.0
+                                step=5.0
+                            />
+                            <Select
+                                name="Brights"
+                                signal=brights
+                                options=[
+                                    BrightDerivation::default_for(PaletteStyle::Dark),
+                                    BrightDerivation::default_for(PaletteStyle::Light),
+                                    BrightDerivation::Offset { lightness: 0.15 },
+                                    BrightDerivation::Apca { delta: 10.0 },
+                                    BrightDerivation::Apca { delta: 20.0 },
+                                ]
+                                    .map(|brights| (brights.to_string(), brights))
+                                    .into()
+                            />
+                            <ValueSlider
+                                name="Min Contrast"
+                                value_signal=min_contrast
+                                min=no_contrast
+                                max=match contrast_metric {
+                                    ContrastMetric::Wcag => 7.0,
+                                    ContrastMetric::Apca => 90.0,
+                                }
+                                step=match contrast_metric {
+                                    ContrastMetric::Wcag => 0.5,
+                                    ContrastMetric::Apca => 5.0,
+                                }
+                            />
+                            <ValueSlider
+                                name="CVD Separation"
+                                value_signal=cvd_min_delta_e
+                                min=0.0
+                                max=0.1
+                                step=0.01
+                            />
+                            <Select
+                                name="Simulate"
+                                signal=simulate
+                                options=iter::once(("none".to_string(), None))
+                                    .chain(
+                                        Deficiency::ALL
+                                            .map(|deficiency| (deficiency.to_string(), Some(deficiency))),
+                                    )
+                                    .collect()
+                            />
                         </div>
                     </div>
                 </div>
                 <div class="grid grid-cols-8 grid-rows-3 gap-x-1 gap-y-1">{color_chips}</div>
-                <CodePreview style=b24_style/>
+                <CodePreview style=display_style/>
             </div>
         </div>
     }
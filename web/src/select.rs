@@ -0,0 +1,35 @@
+use leptos::*;
+
+#[component]
+pub fn Select<T>(
+    name: &'static str,
+    signal: RwSignal<T>,
+    options: Vec<(String, T)>,
+) -> impl IntoView
+where
+    T: 'static + Clone + PartialEq,
+{
+    let name_slug = name.to_lowercase().replace(' ', "-");
+    let values = options.clone();
+    let callback = move |ev| {
+        let label = event_target_value(&ev);
+        if let Some((_, value)) = values.iter().find(|(option, _)| *option == label) {
+            signal.set(value.clone());
+        }
+    };
+    let options = options
+        .into_iter()
+        .map(|(label, value)| {
+            let selected = move || signal.with(|current| *current == value);
+            view! { <option value=&label prop:selected=selected>{&label}</option> }
+        })
+        .collect::<Vec<_>>();
+    view! {
+        <div class="flex flex-row gap-2 place-content-between">
+            <select on:change=callback name=&name_slug class="bg-transparent">
+                {options}
+            </select>
+            <label for=&name_slug>{name}</label>
+        </div>
+    }
+}
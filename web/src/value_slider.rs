@@ -30,7 +30,7 @@ where
                 min=min.to_string()
                 max=max.to_string()
                 step=step.to_string()
-                value=value_signal.get_untracked().to_string()
+                prop:value=move || value_signal().to_string()
             />
             <div class="flex flex-row align-right gap-10">
                 <label for=&name_slug>{name}</label>